@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::thread_rng;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Number of rows and columns of a bingo card
+pub const BINGO_SIZE: usize = 4;
+/// For every this many songs of a round, an entry of a song that is not played is put on the cards
+pub const SONGS_PER_DECOY: usize = 3;
+/// Tries to find a card that differs from all others before a duplicate is accepted
+const CARD_TRIES: usize = 100;
+
+/// Bingo card of a player, squares are given row by row
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct BingoCard {
+  player: String,
+  squares: Vec<String>,
+  /// Squares belonging to songs that have been played
+  marked: Vec<bool>,
+}
+
+/// State of a bingo round. Cards are filled with the titles or artists of the songs of the round and some decoys.
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct Bingo {
+  /// Entries of the songs played so far (without the current one)
+  played: Vec<String>,
+  /// Players with a valid bingo in the order of their claims
+  winners: Vec<String>,
+  // Entries the cards are made of, including the decoys of songs that are not played
+  #[serde(skip)]
+  #[ts(skip)]
+  entries: Vec<String>,
+  #[serde(skip)]
+  #[ts(skip)]
+  cards: Vec<BingoCard>,
+  // Entry of the song playing now, it is not revealed until the song ends
+  #[serde(skip)]
+  #[ts(skip)]
+  current: Option<String>,
+}
+
+impl Bingo {
+  /// Create the bingo of a round with the entries of the played songs and decoys of songs that are not played
+  pub fn new(played: Vec<String>, decoys: Vec<String>) -> Bingo {
+    let mut entries = [played, decoys].concat();
+    entries.sort();
+    entries.dedup();
+    Bingo { played: vec![], winners: vec![], entries, cards: vec![], current: None }
+  }
+
+  /// Get the card of a player, a new card is generated on the first request
+  pub fn card(&mut self, player: &str) -> BingoCard {
+    let squares = match self.cards.iter().find(|c| c.player == player) {
+      Some(card) => card.squares.clone(),
+      None => {
+        let squares = self.new_squares();
+        self.cards.push(BingoCard { player: player.to_string(), squares: squares.clone(), marked: vec![] });
+        squares
+      }
+    };
+    let marked = squares.iter().map(|s| self.played.contains(s)).collect();
+    BingoCard { player: player.to_string(), squares, marked }
+  }
+
+  /// Choose squares for a card that are (if possible) not used in the same combination on another card
+  fn new_squares(&self) -> Vec<String> {
+    let used: Vec<HashSet<&String>> = self.cards.iter().map(|c| c.squares.iter().collect()).collect();
+    let mut squares = vec![];
+    for _ in 0..CARD_TRIES {
+      squares = self.entries.iter().cloned().choose_multiple(&mut thread_rng(), BINGO_SIZE * BINGO_SIZE);
+      let set: HashSet<&String> = squares.iter().collect();
+      if !used.contains(&set) {
+        break;
+      }
+      log::debug!("Generated duplicate bingo card, trying again");
+    }
+    squares.shuffle(&mut thread_rng());
+    squares
+  }
+
+  /// Set the entry of the song that starts playing
+  pub fn play(&mut self, entry: String) {
+    self.reveal();
+    self.current = Some(entry);
+  }
+
+  /// Mark the entry of the current song on all cards
+  pub fn reveal(&mut self) {
+    if let Some(entry) = self.current.take() {
+      self.played.push(entry);
+    }
+  }
+
+  /// Check if the player already has a bingo
+  pub fn winners_contain(&self, player: &str) -> bool {
+    self.winners.iter().any(|w| w == player)
+  }
+
+  /// Check the bingo claim of a player. A card has a bingo if all squares of a row, a column or a diagonal have been
+  /// played (including the current song). Returns the place of the player, `None` if there is no bingo.
+  pub fn claim(&mut self, player: &str) -> Option<usize> {
+    if let Some(place) = self.winners.iter().position(|w| w == player) {
+      return Some(place);
+    }
+    let card = self.cards.iter().find(|c| c.player == player)?;
+    let played = |i: usize| card.squares.get(i)
+      .is_some_and(|s| self.played.contains(s) || self.current.as_ref() == Some(s));
+    let lines = (0..BINGO_SIZE).map(|r| (0..BINGO_SIZE).map(|c| r * BINGO_SIZE + c).collect::<Vec<usize>>())
+      .chain((0..BINGO_SIZE).map(|c| (0..BINGO_SIZE).map(|r| r * BINGO_SIZE + c).collect()))
+      .chain([(0..BINGO_SIZE).map(|i| i * BINGO_SIZE + i).collect(),
+              (0..BINGO_SIZE).map(|i| i * BINGO_SIZE + BINGO_SIZE - 1 - i).collect()]);
+    let has_bingo = lines.into_iter().any(|line| line.into_iter().all(played));
+    if has_bingo {
+      self.winners.push(player.to_string());
+      Some(self.winners.len() - 1)
+    } else {
+      None
+    }
+  }
+}
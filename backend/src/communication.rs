@@ -7,25 +7,43 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use axum::{Extension, extract::Query, extract::ws::{Message, WebSocket}, response::Json};
 use axum::extract::WebSocketUpgrade;
-use axum::response::IntoResponse;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use futures::{sink::SinkExt, stream::{SplitSink, SplitStream, StreamExt}};
 use rspotify::clients::OAuthClient;
 use serde::{Deserialize, Serialize};
-use serde_json;
 use tokio::select;
 use tokio::sync::broadcast::{Receiver, Sender};
 use ts_rs::TS;
 
-use crate::game::{AnswerFromUser, GameCommand, GamePreferences, GameReferences, GameState, ScoreMode};
+use crate::quiz::{QuizSource, MAX_ANSWER_COUNT, MIN_ANSWER_COUNT};
+use crate::spotify::resolve_source;
+use crate::titles::{compile, TitleRule};
+use crate::game::{AnswerFromUser, AppStatus, BingoContent, BuzzFromUser, CoverEffect, DistractorSource, DistractorStrategy, GameCommand, GamePreferences,
+                  GameReferences, GameState, PoolPlaylist, ScoreMode, SnippetStart};
 
 //---------------------------------------------- POST Routes -----------------------------------------------------------
 
-pub async fn select_answer(Extension(state): Extension<Arc<RwLock<GameState>>>, answer: Json<AnswerFromUser>) -> Json<GameState> {
+pub async fn select_answer(Extension(state): Extension<Arc<RwLock<GameState>>>,
+                           Extension(references): Extension<Arc<Mutex<GameReferences>>>,
+                           answer: Json<AnswerFromUser>) -> Json<GameState> {
+  // Always lock references first to avoid deadlock!
+  let tx_commands = references.lock().await.tx_commands.clone();
   let mut s = state.write().await;
-  if let Err(err) = s.give_answer(answer.deref().clone()) {
-    log::warn!("Error on giving answer: {:?}", err);
+  let result = s.give_answer(answer.deref().clone());
+  let exposed = s.exposed();
+  drop(s);
+  match result {
+    Err(err) => log::warn!("Error on giving answer: {:?}", err),
+    // The game decides if the song continues after the answer of a buzzing player
+    Ok(()) if exposed.status() == AppStatus::InGameBuzzed => {
+      if let Err(e) = tx_commands.send(GameCommand::BuzzerAnswered).await {
+        log::warn!("Error on sending buzzer answer {:?}", e);
+      }
+    }
+    Ok(()) => {}
   }
-  Json(s.clone())
+  Json(exposed)
 }
 
 pub async fn start_game(Extension(references): Extension<Arc<Mutex<GameReferences>>>) {
@@ -67,11 +85,85 @@ pub struct PreferenceParams {
   hide_answers: Option<bool>,
   ask_for_artist: Option<bool>,
   ask_for_title: Option<bool>,
+  artist_separator: Option<String>,
+  free_text: Option<bool>,
+  text_tolerance: Option<u32>,
+  ask_for_album: Option<bool>,
+  ask_for_year: Option<bool>,
+  ask_for_order: Option<bool>,
+  ask_for_cover: Option<bool>,
+  ask_for_artist_and_title: Option<bool>,
+  answer_count: Option<u32>,
+  answer_count_ramp: Option<bool>,
+  progressive_reveal: Option<bool>,
+  buzzer_mode: Option<bool>,
+  cover_effect: Option<CoverEffect>,
+  timeline_mode: Option<bool>,
+  bingo_mode: Option<bool>,
+  bingo_content: Option<BingoContent>,
+  /// Spotify URI or link of an album, artist or playlist to be added as track pool
+  add_source: Option<String>,
+  /// Id of a track pool added with `add_source` to be removed
+  remove_source: Option<String>,
+  /// Id of a playlist to be added to the playlist pool (or whose weight should be changed)
+  add_to_pool: Option<String>,
+  /// Weight of the playlist given by `add_to_pool`, 1 if not given
+  weight: Option<u32>,
+  /// Id of a playlist to be removed from the playlist pool
+  remove_from_pool: Option<String>,
+  distractor_source: Option<DistractorSource>,
+  distractor_strategy: Option<DistractorStrategy>,
+  snippet_start: Option<SnippetStart>,
+  snippet_offset: Option<u32>,
+  /// Built-in title rule to be enabled
+  enable_title_rule: Option<TitleRule>,
+  /// Built-in title rule to be disabled
+  disable_title_rule: Option<TitleRule>,
+  /// Regular expression for parts to be removed from song titles
+  add_title_pattern: Option<String>,
+  /// Custom title pattern to be removed
+  remove_title_pattern: Option<String>,
 }
 
-pub async fn set_preference(Extension(preferences): Extension<Arc<Mutex<GamePreferences>>>, params: Query<PreferenceParams>)
-                            -> Json<GamePreferences> {
+pub async fn set_preference(Extension(preferences): Extension<Arc<Mutex<GamePreferences>>>,
+                            Extension(references): Extension<Arc<Mutex<GameReferences>>>,
+                            params: Query<PreferenceParams>) -> Result<Json<GamePreferences>, (StatusCode, String)> {
+  // Resolve new sources first, references need to be locked before preferences
+  let mut new_sources = vec![];
+  if let Some(uri) = &params.add_source {
+    let r = references.lock().await;
+    let spotify = r.spotify_client.clone();
+    drop(r);
+    match resolve_source(&spotify, uri).await {
+      Ok(sources) => new_sources = sources,
+      Err(e) => log::warn!("Could not add source {}: {}", uri, e)
+    }
+  }
+
   let mut p = preferences.lock().await;
+  // A round takes its songs from one source, playlists of other sources in the pool would be skipped
+  if let Some(id) = &params.add_to_pool {
+    let source = QuizSource::from_playlist_id(id);
+    if let Some(other) = p.pool().iter().find(|x| QuizSource::from_playlist_id(&x.playlist.id) != source) {
+      log::warn!("Rejected adding {} to the pool, {} is from another source", id, other.playlist.name);
+      return Err((StatusCode::BAD_REQUEST,
+                  format!("{} is from another source than {} in the pool", id, other.playlist.name)));
+    }
+  }
+  for source in new_sources {
+    if !p.spotify_sources.iter().any(|s| s.id == source.id) {
+      log::info!("add source {:?}", source);
+      p.spotify_sources.push(source.clone());
+      p.playlists.push(source);
+    }
+  }
+  if let Some(id) = &params.remove_source {
+    if p.spotify_sources.iter().any(|s| s.id == *id) {
+      log::info!("remove source {}", id);
+      p.spotify_sources.retain(|s| s.id != *id);
+      p.playlists.retain(|s| s.id != *id);
+    }
+  }
   if let Some(sm) = params.scoremode {
     log::info!("set scoremode to {:?}", sm);
     p.scoremode = sm;
@@ -80,8 +172,73 @@ pub async fn set_preference(Extension(preferences): Extension<Arc<Mutex<GamePref
     if let Some(selected_playlist) = p.playlists.iter().find(|x| x.id == *id) {
       log::info!("set playlist to {:?}", selected_playlist);
       p.selected_playlist = Some(selected_playlist.clone());
+      p.playlist_pool.clear();
+    }
+  }
+  if let Some(id) = &params.add_to_pool {
+    if let Some(playlist) = p.playlists.iter().find(|x| x.id == *id).cloned() {
+      let weight = params.weight.unwrap_or(1);
+      log::info!("add playlist {:?} with weight {} to pool", playlist, weight);
+      p.playlist_pool = p.pool();
+      match p.playlist_pool.iter_mut().find(|x| x.playlist.id == *id) {
+        Some(entry) => entry.weight = weight,
+        None => p.playlist_pool.push(PoolPlaylist { playlist, weight })
+      }
+    }
+  }
+  if let Some(id) = &params.remove_from_pool {
+    log::info!("remove playlist {} from pool", id);
+    p.playlist_pool = p.pool();
+    p.playlist_pool.retain(|x| x.playlist.id != *id);
+    // Without a pool, the selected playlist would be used again
+    if p.selected_playlist.as_ref().is_some_and(|s| s.id == *id) {
+      p.selected_playlist = None;
+    }
+  }
+  // The selected playlist is the first one of the pool
+  if let Some(first) = p.playlist_pool.first() {
+    p.selected_playlist = Some(first.playlist.clone());
+  }
+  if let Some(d) = params.distractor_source {
+    log::info!("set distractor_source to {:?}", d);
+    p.distractor_source = d;
+  }
+  if let Some(d) = params.distractor_strategy {
+    log::info!("set distractor_strategy to {:?}", d);
+    p.distractor_strategy = d;
+  }
+  if let Some(s) = params.snippet_start {
+    log::info!("set snippet_start to {:?}", s);
+    p.snippet_start = s;
+  }
+  if let Some(o) = params.snippet_offset {
+    log::info!("set snippet_offset to {}", o);
+    p.snippet_offset = o;
+  }
+  if let Some(rule) = params.enable_title_rule {
+    if !p.title_rules.contains(&rule) {
+      log::info!("enabled title rule {:?}", rule);
+      p.title_rules.push(rule);
+    }
+  }
+  if let Some(rule) = params.disable_title_rule {
+    log::info!("disabled title rule {:?}", rule);
+    p.title_rules.retain(|r| *r != rule);
+  }
+  if let Some(pattern) = &params.add_title_pattern {
+    match compile(pattern) {
+      Ok(_) if !p.title_patterns.contains(pattern) => {
+        log::info!("added title pattern {}", pattern);
+        p.title_patterns.push(pattern.clone());
+      }
+      Ok(_) => {}
+      Err(e) => log::warn!("Invalid title pattern {}: {}", pattern, e)
     }
   }
+  if let Some(pattern) = &params.remove_title_pattern {
+    log::info!("removed title pattern {}", pattern);
+    p.title_patterns.retain(|x| x != pattern);
+  }
   if let Some(t) = params.time_to_answer {
     log::info!("set time_to_answer to {}", t);
     p.time_to_answer = t;
@@ -106,23 +263,93 @@ pub async fn set_preference(Extension(preferences): Extension<Arc<Mutex<GamePref
     log::info!("set hide_answers to {}", m);
     p.hide_answers = m;
   }
+  if let Some(sep) = &params.artist_separator {
+    log::info!("set artist_separator to {:?}", sep);
+    p.artist_separator = sep.clone();
+  }
+  if let Some(c) = params.answer_count {
+    p.answer_count = c.clamp(MIN_ANSWER_COUNT, MAX_ANSWER_COUNT);
+    log::info!("set answer_count to {}", p.answer_count);
+  }
+  if let Some(r) = params.answer_count_ramp {
+    log::info!("set answer_count_ramp to {}", r);
+    p.answer_count_ramp = r;
+  }
+  if let Some(f) = params.free_text {
+    log::info!("set free_text to {}", f);
+    p.free_text = f;
+  }
+  if let Some(t) = params.text_tolerance {
+    p.text_tolerance = t.min(100);
+    log::info!("set text_tolerance to {}", p.text_tolerance);
+  }
   if let Some(a) = params.ask_for_title {
-    p.ask_for_title = if p.ask_for_artist { a } else { true };
-    log::info!("set ask_for_title to {}", p.ask_for_title);
+    p.ask_for_title = a;
+    log::info!("set ask_for_title to {}", a);
   }
   if let Some(a) = params.ask_for_artist {
-    p.ask_for_artist = if p.ask_for_title { a } else { true };
-    log::info!("set ask_for_title to {}", p.ask_for_artist);
+    p.ask_for_artist = a;
+    log::info!("set ask_for_artist to {}", a);
+  }
+  if let Some(a) = params.ask_for_album {
+    p.ask_for_album = a;
+    log::info!("set ask_for_album to {}", a);
+  }
+  if let Some(a) = params.ask_for_year {
+    p.ask_for_year = a;
+    log::info!("set ask_for_year to {}", a);
+  }
+  if let Some(a) = params.ask_for_order {
+    p.ask_for_order = a;
+    log::info!("set ask_for_order to {}", a);
+  }
+  if let Some(a) = params.ask_for_artist_and_title {
+    p.ask_for_artist_and_title = a;
+    log::info!("set ask_for_artist_and_title to {}", a);
+  }
+  if let Some(a) = params.ask_for_cover {
+    p.ask_for_cover = a;
+    log::info!("set ask_for_cover to {}", a);
+  }
+  if let Some(e) = params.cover_effect {
+    log::info!("set cover_effect to {:?}", e);
+    p.cover_effect = e;
+  }
+  if let Some(r) = params.progressive_reveal {
+    log::info!("set progressive_reveal to {}", r);
+    p.progressive_reveal = r;
+  }
+  if let Some(b) = params.buzzer_mode {
+    log::info!("set buzzer_mode to {}", b);
+    p.buzzer_mode = b;
+  }
+  if let Some(t) = params.timeline_mode {
+    log::info!("set timeline_mode to {}", t);
+    p.timeline_mode = t;
+  }
+  if let Some(b) = params.bingo_mode {
+    log::info!("set bingo_mode to {}", b);
+    p.bingo_mode = b;
+  }
+  if let Some(c) = params.bingo_content {
+    log::info!("set bingo_content to {:?}", c);
+    p.bingo_content = c;
+  }
+  // At least one kind of question has to stay enabled
+  if !(p.ask_for_title || p.ask_for_artist || p.ask_for_album || p.ask_for_year || p.ask_for_order || p.ask_for_cover
+    || p.ask_for_artist_and_title) {
+    log::info!("No kind of question enabled, set ask_for_title to true");
+    p.ask_for_title = true;
   }
   let new_preferences = p.clone();
   drop(p);
   save_preferences(&new_preferences, crate::PREFERENCES_FILE);
-  Json(new_preferences)
+  Ok(Json(new_preferences))
 }
 
 fn save_preferences(new_preferences: &GamePreferences, to: &str) {
   match fs::File::create(to) {
-    Ok(file) => match serde_json::to_writer_pretty::<fs::File, GamePreferences>(file, &new_preferences) {
+    Ok(file) => match serde_json::to_writer_pretty::<fs::File, GamePreferences>(file, new_preferences) {
       Ok(_) => log::info!("Saved preferences to file"),
       Err(e) => log::warn!("Could not save preferences to file ({:?})", e)
     },
@@ -152,7 +379,7 @@ pub async fn stop_game(Extension(references): Extension<Arc<Mutex<GameReferences
 
 pub async fn get_state(Extension(state): Extension<Arc<RwLock<GameState>>>) -> Json<GameState> {
   let s = state.read().await;
-  Json(s.clone())
+  Json(s.exposed())
 }
 
 pub async fn get_preferences(Extension(preferences): Extension<Arc<Mutex<GamePreferences>>>)
@@ -161,6 +388,44 @@ pub async fn get_preferences(Extension(preferences): Extension<Arc<Mutex<GamePre
   Json(p.clone())
 }
 
+/// Get the audio file of the current question, only available while the question can be answered
+pub async fn get_question_audio(Extension(state): Extension<Arc<RwLock<GameState>>>,
+                                Query(params): Query<HashMap<String, i32>>) -> Response {
+  let s = state.read().await;
+  match params.get("index").and_then(|&index| s.current_audio(index)) {
+    Some(audio) => ([(header::CONTENT_TYPE, audio.content_type.clone())], audio.data.clone()).into_response(),
+    None => StatusCode::NOT_FOUND.into_response()
+  }
+}
+
+/// Get the bingo card of the player given by `user`
+pub async fn get_bingo_card(Extension(state): Extension<Arc<RwLock<GameState>>>,
+                            Query(params): Query<HashMap<String, String>>) -> Response {
+  let mut s = state.write().await;
+  match params.get("user").and_then(|user| s.bingo_card(user)) {
+    Some(card) => Json(card).into_response(),
+    None => StatusCode::NOT_FOUND.into_response()
+  }
+}
+
+/// Claim a bingo for the player given by `user`
+pub async fn claim_bingo(Extension(state): Extension<Arc<RwLock<GameState>>>,
+                         Extension(references): Extension<Arc<Mutex<GameReferences>>>,
+                         Query(params): Query<HashMap<String, String>>) -> Json<GameState> {
+  // Always lock references first to avoid deadlock!
+  let tx_broadcast = references.lock().await.tx_broadcast.clone();
+  let mut s = state.write().await;
+  if let Some(user) = params.get("user") {
+    match s.claim_bingo(user) {
+      Ok(()) => {
+        let _ = tx_broadcast.send(s.deref().into());
+      }
+      Err(e) => log::warn!("Bingo claim of {} rejected: {:?}", user, e)
+    }
+  }
+  Json(s.exposed())
+}
+
 #[derive(Serialize, TS)]
 #[ts(export)]
 #[ts(export_to = "../shared/")]
@@ -203,6 +468,7 @@ impl SpotifyPrefs {
                    "user-read-currently-playing".to_string(),
                    "playlist-read-collaborative".to_string(),
                    "playlist-read-private".to_string(),
+                   "user-library-read".to_string(),
                    "app-remote-control".to_string(),
                    "streaming".to_string(),
                    "user-read-email".to_string(),
@@ -231,6 +497,7 @@ pub enum DataType {
   Answer,
   GameState,
   Time,
+  Buzz,
 }
 
 #[derive(Deserialize, Serialize, TS)]
@@ -246,21 +513,22 @@ pub async fn ws_handler(ws: WebSocketUpgrade, Extension(state): Extension<Arc<Rw
   let r = references.lock().await;
   let tx_broadcast = r.tx_broadcast.clone();
   let rx_broadcast = r.tx_broadcast.subscribe();
+  let tx_commands = r.tx_commands.clone();
   drop(r);
   ws.on_upgrade(|socket| async move {
     log::debug!("Client connected");
     let (sender, receiver) = socket.split();
     let (tx, rx) = tokio::sync::mpsc::channel::<Message>(8);
 
-    tokio::spawn(read_socket(receiver, state.clone(), tx_broadcast, tx));
+    tokio::spawn(read_socket(receiver, state.clone(), tx_broadcast, tx_commands, tx));
     tokio::spawn(write_socket(sender, state, rx_broadcast, rx));
   })
 }
 
 async fn read_socket(mut receiver: SplitStream<WebSocket>, state: Arc<RwLock<GameState>>, tx_broadcast: Sender<Message>,
-                     tx_single: tokio::sync::mpsc::Sender<Message>) {
+                     tx_commands: tokio::sync::mpsc::Sender<GameCommand>, tx_single: tokio::sync::mpsc::Sender<Message>) {
   while let Some(result) = receiver.next().await {
-    // Only thing that can be received is an answer (currently)
+    // Answers, buzzes and time requests can be received
     match result {
       Ok(ws_msg) => {
         match serde_json::from_str::<WebSocketMessage>(ws_msg.into_text().unwrap().as_str()) {
@@ -278,12 +546,41 @@ async fn read_socket(mut receiver: SplitStream<WebSocket>, state: Arc<RwLock<Gam
                       if let Err(e) = tx_broadcast.send(s.deref().into()) {
                         log::warn!("Error on sending broadcast {:?}", e);
                       }
+                      // The game decides if the song continues after the answer of a buzzing player
+                      let buzzed = s.status() == AppStatus::InGameBuzzed;
+                      drop(s);
+                      if buzzed {
+                        if let Err(e) = tx_commands.send(GameCommand::BuzzerAnswered).await {
+                          log::warn!("Error on sending buzzer answer {:?}", e);
+                        }
+                      }
                     }
                   },
                   Err(e) => log::warn!("Received invalid answer {:?}!", e),
                 }
               }
 
+              DataType::Buzz => {
+                // User buzzed, the question is paused until the answer
+                match serde_json::from_str::<BuzzFromUser>(msg.data.as_str()) {
+                  Ok(buzz) => {
+                    let mut s = state.write().await;
+                    if let Err(err) = s.buzz(buzz) {
+                      log::warn!("Error on buzzing: {:?}", err);
+                    } else {
+                      if let Err(e) = tx_broadcast.send(s.deref().into()) {
+                        log::warn!("Error on sending broadcast {:?}", e);
+                      }
+                      drop(s);
+                      if let Err(e) = tx_commands.send(GameCommand::Buzz).await {
+                        log::warn!("Error on sending buzz {:?}", e);
+                      }
+                    }
+                  }
+                  Err(e) => log::warn!("Received invalid buzz {:?}!", e),
+                }
+              }
+
               DataType::Time => {
                 // User sent his timestamp, answer with diff
                 match serde_json::from_str::<TimeRequest>(msg.data.as_str()) {
@@ -391,7 +688,7 @@ async fn write_socket(mut sender: SplitSink<WebSocket, Message>, state: Arc<RwLo
 
 impl From<&GameState> for Message {
   fn from(state: &GameState) -> Self {
-    let state_json = serde_json::to_string::<GameState>(&state).unwrap();
+    let state_json = serde_json::to_string::<GameState>(&state.exposed()).unwrap();
     let ws_msg = WebSocketMessage { message_type: DataType::GameState, data: state_json };
     Message::Text(serde_json::to_string::<WebSocketMessage>(&ws_msg).unwrap())
   }
@@ -399,7 +696,7 @@ impl From<&GameState> for Message {
 
 impl From<&TimeAnswer> for Message {
   fn from(ans: &TimeAnswer) -> Self {
-    let time_answer_json = serde_json::to_string::<TimeAnswer>(&ans).unwrap();
+    let time_answer_json = serde_json::to_string::<TimeAnswer>(ans).unwrap();
     let ws_msg = WebSocketMessage { message_type: DataType::Time, data: time_answer_json };
     Message::Text(serde_json::to_string::<WebSocketMessage>(&ws_msg).unwrap())
   }
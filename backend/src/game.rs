@@ -1,5 +1,6 @@
 use std::cmp::min;
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::{Arc};
 use tokio::sync::{Mutex, RwLock, mpsc};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -8,7 +9,15 @@ use serde::{Deserialize, Serialize};
 use rspotify::AuthCodeSpotify;
 use tokio::sync::broadcast::Sender;
 use crate::game::GameError::{AnswerNotAllowed, InvalidState};
-use crate::quiz::{QuizError, SongQuiz};
+use crate::bingo::{Bingo, BingoCard};
+use crate::local::LocalQuiz;
+use crate::matching::text_matches;
+use crate::mpd::{MpdClient, MpdQuiz};
+use crate::player::{self, AudioPlayer};
+use crate::subsonic::{SubsonicClient, SubsonicQuiz};
+use crate::titles::{default_title_rules, TitleRule};
+use crate::track_cache::TrackCache;
+use crate::quiz::{QuestionAudio, Quiz, QuizError, QuizSource, SongQuiz};
 use ts_rs::TS;
 
 const MAX_POINTS_CORRECT_ANSWER: i32 = 100; /// Maximum points for correct answer
@@ -16,6 +25,14 @@ const MIN_POINTS_CORRECT_ANSWER: i32 = 20;  /// Minimum points for correct answe
 const TIME_FULL_POINTS_MS: u32 = 1000;  /// Time after question start in which full points are given (in time score mode)
 const POINTS_TIME: [f32; 6] = [0.0, 800.0, 1300.0, 2000.0, 3000.0, 10000.0];
 const POINTS_AMOUNT: [i32; 6] = [100, 100, 80, 60, 50, 20];
+const MAX_TEXT_ANSWER_LENGTH: usize = 200; /// Typed answers are cut after this number of characters
+const NUMBER_TOLERANCE: i32 = 10; /// Difference to the correct number from which no points are given for number answers
+const REVEAL_STAGES: [u64; 4] = [1, 2, 4, 8]; /// Seconds of the song played in the stages of a progressive reveal
+const REVEAL_PAUSE_MS: u64 = 2000; /// Pause after every stage of a progressive reveal
+const BUZZER_ANSWER_MS: u64 = 5000; /// Time a player has to answer after buzzing
+const PARTS_BONUS: f32 = 0.5; /// Share of the points given in addition if all parts of a question are correct
+/// Separates the answers for the parts of a question in answer ids and correct answers
+pub const PART_SEPARATOR: &str = "\n";
 
 #[derive(Serialize, Clone, TS)]
 #[ts(export)]
@@ -25,6 +42,8 @@ pub struct UserAnswerExposed {
   user: String,
   #[ts(type = "number")]
   ts: u64,
+  /// Stage of the progressive reveal in which the answer was locked in
+  stage: Option<u32>,
 }
 
 #[derive(Serialize, Clone, TS)]
@@ -35,16 +54,87 @@ pub struct AnswerExposed {
   pub id: String,
 }
 
+/// How a question is answered
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub enum AnswerKind {
+  /// Select one of `answers`
+  Choice,
+  /// Enter a number between `min` and `max` (as answer id), points depend on the distance to the correct number
+  Number { min: i32, max: i32 },
+  /// Type the answer (as answer text), small typos are accepted
+  Text,
+  /// No answer, the song is marked on the bingo cards (see `GameState::bingo`)
+  Bingo,
+  /// Insert the song into the own timeline (`position` of the answer), the correct answer is the release year
+  Timeline,
+  /// Bring all `answers` in the correct order (ids separated by commas), points for every pair in the right order
+  Order,
+  /// Answer all `parts` (`parts` of the answer), every correct part gives a share of the points, all together a bonus
+  Parts,
+}
+
+/// Part of a question that is answered together with the others, e.g. artist and title
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct QuestionPart {
+  pub text: String,
+  /// Answers to choose from, the answer is typed if empty
+  pub answers: Vec<AnswerExposed>,
+  /// Further answers accepted for typed answers
+  #[serde(skip)]
+  #[ts(skip)]
+  pub alternatives: Vec<String>,
+}
+
 #[derive(Serialize, Clone, TS)]
 #[ts(export)]
 #[ts(export_to = "../shared/")]
 pub struct Question {
   pub text: String,
+  pub kind: AnswerKind,
   pub answers: Vec<AnswerExposed>,
+  pub parts: Vec<QuestionPart>,
   pub correct: Option<String>,
   pub solution: Option<String>,
   pub index: i32,
   pub total_questions: u32,
+  /// Indices of the songs of the quiz played one after another for this question
+  #[serde(skip)]
+  #[ts(skip)]
+  pub clips: Vec<usize>,
+  /// Further answers accepted for typed answers
+  #[serde(skip)]
+  #[ts(skip)]
+  pub alternatives: Vec<String>,
+  /// Picture shown instead of playing the song
+  pub media: Option<QuestionMedia>,
+  /// Bingo entry of a song that is not played in the round, it is only put on the cards
+  #[serde(skip)]
+  #[ts(skip)]
+  pub decoy: Option<String>,
+}
+
+/// Picture of a question that is obscured at first. It gets sharper from `action_start` and is fully visible at
+/// `next_action` of the question.
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct QuestionMedia {
+  pub url: String,
+  pub effect: CoverEffect,
+}
+
+/// How the picture of a question is obscured
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub enum CoverEffect {
+  #[default]
+  Blur,
+  Pixelate,
 }
 
 #[derive(Serialize, Clone, TS)]
@@ -81,10 +171,41 @@ pub enum AppStatus {
   BeforeGame,
   Preparing,
   InGameAnswerPending,
+  /// Question in buzzer mode, players buzz before they can answer
+  InGameBuzzerOpen,
+  /// A player has buzzed and answers alone (see `GameState::buzzer`)
+  InGameBuzzed,
   InGameWaitForNextQuestion,
   BetweenRounds,
 }
 
+/// Song in the timeline of a player
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct TimelineCard {
+  song: String,
+  year: i32,
+}
+
+/// Songs a player has placed correctly in timeline mode, sorted by release year
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct PlayerTimeline {
+  player: String,
+  cards: Vec<TimelineCard>,
+}
+
+impl PlayerTimeline {
+  /// Check if a song of the given year belongs at `position`
+  fn fits(&self, position: usize, year: i32) -> bool {
+    position <= self.cards.len()
+      && (position == 0 || self.cards[position - 1].year <= year)
+      && (position == self.cards.len() || year <= self.cards[position].year)
+  }
+}
+
 // Public game management structure
 #[derive(Serialize, Clone, TS)]
 #[ts(export)]
@@ -98,7 +219,46 @@ pub struct GameState {
   current_question: Option<Question>,
   players: Vec<PlayerScoreAPI>,
   given_answers: Vec<UserAnswerExposed>,
-  hide_answers: bool
+  hide_answers: bool,
+  timelines: Vec<PlayerTimeline>,
+  bingo: Option<Bingo>,
+  /// Stage of the progressive reveal that is played (see `REVEAL_STAGES`)
+  reveal_stage: Option<u32>,
+  /// Player who buzzed and may answer now
+  buzzer: Option<String>,
+  /// Players who answered wrong after buzzing and may not buzz again for the current question
+  locked_out: Vec<String>,
+  // Time to answer left when the question was paused by a buzz (ms)
+  #[serde(skip)]
+  #[ts(skip)]
+  remaining: u64,
+  // Start of the current question (ms after epoch), `action_start` is moved when the question is paused by a buzz
+  #[serde(skip)]
+  #[ts(skip)]
+  question_start: u64,
+  audio: Option<AudioInfo>,
+  // Audio of the current question, served separately (see `audio`)
+  #[serde(skip)]
+  #[ts(skip)]
+  question_audio: Option<QuestionAudio>,
+}
+
+/// Information for clients to play the audio of the current question synchronously with the others
+#[derive(Serialize, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct AudioInfo {
+  /// Where to get the audio file from
+  url: String,
+  /// Time (ms after epoch on the server, see `/get_time` for the offset) at which the playback starts
+  #[ts(type = "number")]
+  start_time: u64,
+  /// Position in the audio file (ms) at which the playback starts
+  #[ts(type = "number")]
+  position: u64,
+  /// How long the audio is played (ms), until the end of the question if not given
+  #[ts(type = "number | null")]
+  length: Option<u64>,
 }
 
 // Internal game management structure
@@ -107,7 +267,14 @@ pub struct GameReferences {
   pub tx_spotify: mpsc::Sender<()>,
   pub spotify_client: Arc<AuthCodeSpotify>,
   pub tx_broadcast: tokio::sync::broadcast::Sender<Message>,
+  #[allow(dead_code)] // keeps the broadcast channel open while no client is connected
   pub rx_broadcast: tokio::sync::broadcast::Receiver<Message>,
+  pub music_dir: Option<PathBuf>,
+  pub playlist_dir: Option<PathBuf>,
+  pub subsonic_client: Option<Arc<SubsonicClient>>,
+  pub mpd_client: Option<MpdClient>,
+  pub player: AudioPlayer,
+  pub track_cache: TrackCache,
 }
 
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, TS)]
@@ -128,6 +295,70 @@ pub struct Playlist {
   pub id: String,
 }
 
+/// Playlist of the pool questions are drawn from. The chance of a question being taken from a playlist is
+/// proportional to its weight, playlists with weight 0 only provide wrong answers.
+#[derive(Serialize, Deserialize, Clone, Debug, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct PoolPlaylist {
+  pub playlist: Playlist,
+  pub weight: u32,
+}
+
+/// Where the wrong answers of a question are taken from
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub enum DistractorSource {
+  /// All songs of the playlist pool
+  #[default]
+  Pool,
+  /// Only songs of the playlist the asked song belongs to
+  OwnPlaylist,
+}
+
+/// How the wrong answers of a question are chosen
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub enum DistractorStrategy {
+  /// Randomly from all songs
+  #[default]
+  Uniform,
+  /// Songs released in the same decade
+  SameDecade,
+  /// Other songs of the same artist (for title questions)
+  SameArtist,
+  /// Songs with a similar popularity on Spotify
+  SimilarPopularity,
+}
+
+/// Where in a song the played snippet starts
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub enum SnippetStart {
+  /// At a third of the song
+  #[default]
+  Third,
+  /// At the beginning of the song
+  Intro,
+  /// At `snippet_offset` seconds
+  Fixed,
+  /// Randomly between `snippet_offset` seconds and the end of the song
+  Random,
+}
+
+/// What the squares of bingo cards show
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub enum BingoContent {
+  #[default]
+  Titles,
+  Artists,
+}
+
 #[derive(Serialize, Deserialize, Clone, TS)]
 #[ts(export)]
 #[ts(export_to = "../shared/")]
@@ -142,7 +373,84 @@ pub struct GamePreferences {
   pub preview_mode: bool,
   pub hide_answers: bool,
   pub ask_for_artist: bool,
-  pub ask_for_title: bool
+  pub ask_for_title: bool,
+  /// Players type the answers instead of choosing from some
+  #[serde(default)]
+  pub free_text: bool,
+  /// Text between the names of songs with several artists
+  #[serde(default = "default_artist_separator")]
+  pub artist_separator: String,
+  /// Percentage of characters that may be wrong in typed answers
+  #[serde(default = "default_text_tolerance")]
+  pub text_tolerance: u32,
+  #[serde(default)]
+  pub ask_for_album: bool,
+  #[serde(default)]
+  pub ask_for_year: bool,
+  /// Several songs are played and have to be ordered by release date
+  #[serde(default)]
+  pub ask_for_order: bool,
+  /// Album covers are shown instead of playing songs, players guess the album or artist (Spotify only)
+  #[serde(default)]
+  pub ask_for_cover: bool,
+  #[serde(default)]
+  pub cover_effect: CoverEffect,
+  /// Artist and title are asked at once
+  #[serde(default)]
+  pub ask_for_artist_and_title: bool,
+  /// Number of answers to choose from
+  #[serde(default = "default_answer_count")]
+  pub answer_count: u32,
+  /// Later questions of a round have more answers, up to the maximum for the last one
+  #[serde(default)]
+  pub answer_count_ramp: bool,
+  /// Players buzz to answer alone, the song is paused meanwhile
+  #[serde(default)]
+  pub buzzer_mode: bool,
+  /// Songs are played from the beginning in stages of 1, 2, 4 and 8 seconds, later answers give fewer points
+  #[serde(default)]
+  pub progressive_reveal: bool,
+  /// Players build a timeline of songs instead of answering questions
+  #[serde(default)]
+  pub timeline_mode: bool,
+  /// Players get bingo cards with the songs of the round instead of answering questions
+  #[serde(default)]
+  pub bingo_mode: bool,
+  #[serde(default)]
+  pub bingo_content: BingoContent,
+  /// Albums and artists added as track pools, they are offered in `playlists` in addition to the user's playlists
+  #[serde(default)]
+  pub spotify_sources: Vec<Playlist>,
+  /// Playlists to combine for a round, only `selected_playlist` is used if empty
+  #[serde(default)]
+  pub playlist_pool: Vec<PoolPlaylist>,
+  #[serde(default)]
+  pub distractor_source: DistractorSource,
+  #[serde(default)]
+  pub distractor_strategy: DistractorStrategy,
+  #[serde(default)]
+  pub snippet_start: SnippetStart,
+  /// Seconds from the beginning of the song for `SnippetStart::Fixed` and `SnippetStart::Random`
+  #[serde(default)]
+  pub snippet_offset: u32,
+  /// Built-in rules removing parts like "- Remastered 2011" from song titles
+  #[serde(default = "default_title_rules")]
+  pub title_rules: Vec<TitleRule>,
+  /// Additional regular expressions for parts to be removed from song titles
+  #[serde(default)]
+  pub title_patterns: Vec<String>,
+}
+
+fn default_artist_separator() -> String {
+  ", ".to_string()
+}
+
+fn default_answer_count() -> u32 {
+  4
+}
+
+fn default_text_tolerance() -> u32 {
+  20
 }
 
 impl GamePreferences {
@@ -158,7 +466,40 @@ impl GamePreferences {
       preview_mode: false,
       hide_answers: false,
       ask_for_artist: true,
-      ask_for_title: true
+      ask_for_title: true,
+      artist_separator: default_artist_separator(),
+      free_text: false,
+      text_tolerance: default_text_tolerance(),
+      ask_for_album: false,
+      ask_for_year: false,
+      ask_for_order: false,
+      ask_for_cover: false,
+      ask_for_artist_and_title: false,
+      answer_count: default_answer_count(),
+      answer_count_ramp: false,
+      progressive_reveal: false,
+      buzzer_mode: false,
+      cover_effect: CoverEffect::Blur,
+      timeline_mode: false,
+      bingo_mode: false,
+      bingo_content: BingoContent::Titles,
+      spotify_sources: vec![],
+      playlist_pool: vec![],
+      distractor_source: DistractorSource::Pool,
+      distractor_strategy: DistractorStrategy::Uniform,
+      snippet_start: SnippetStart::Third,
+      snippet_offset: 0,
+      title_rules: default_title_rules(),
+      title_patterns: vec![],
+    }
+  }
+
+  /// Get the playlists questions are drawn from
+  pub fn pool(&self) -> Vec<PoolPlaylist> {
+    if self.playlist_pool.is_empty() {
+      self.selected_playlist.iter().map(|p| PoolPlaylist { playlist: p.clone(), weight: 1 }).collect()
+    } else {
+      self.playlist_pool.clone()
     }
   }
 }
@@ -169,6 +510,10 @@ impl GamePreferences {
 pub enum GameCommand {
   StartGame,
   StopGame,
+  /// A player has buzzed (see `GameState::buzz`)
+  Buzz,
+  /// The player who buzzed has answered
+  BuzzerAnswered,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, TS)]
@@ -176,11 +521,27 @@ pub enum GameCommand {
 #[ts(export_to = "../shared/")]
 pub struct AnswerFromUser {
   id: String,
+  /// Position in the own timeline for questions of kind `Timeline`
+  #[serde(default)]
+  position: Option<u32>,
+  /// Typed answer for questions of kind `Text`
+  #[serde(default)]
+  text: Option<String>,
+  /// Answer id (or typed answer) for every part of questions of kind `Parts`, empty for parts not answered
+  #[serde(default)]
+  parts: Option<Vec<String>>,
   #[ts(type = "number")]
   timestamp: u64,
   user: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub struct BuzzFromUser {
+  user: String,
+}
+
 impl GameState {
   pub fn new() -> GameState {
     GameState {
@@ -190,15 +551,123 @@ impl GameState {
       current_question: None,
       players: vec![],
       given_answers: vec![],
-      hide_answers: false
+      hide_answers: false,
+      timelines: vec![],
+      bingo: None,
+      reveal_stage: None,
+      buzzer: None,
+      locked_out: vec![],
+      remaining: 0,
+      question_start: 0,
+      audio: None,
+      question_audio: None,
     }
   }
 
-  /// Receive an answer from a user
-  pub fn give_answer(&mut self, answer: AnswerFromUser) -> Result<(), GameError> {
-    if self.status != AppStatus::InGameAnswerPending {
+  /// Get the audio of the current question if it is still to be answered
+  pub fn current_audio(&self, index: i32) -> Option<&QuestionAudio> {
+    match &self.current_question {
+      Some(q) if q.index == index && matches!(self.status, AppStatus::InGameAnswerPending
+        | AppStatus::InGameBuzzerOpen | AppStatus::InGameBuzzed) => self.question_audio.as_ref(),
+      _ => None
+    }
+  }
+
+  /// Get the bingo card of a player, `None` if no bingo round is running
+  pub fn bingo_card(&mut self, player: &str) -> Option<BingoCard> {
+    self.bingo.as_mut().map(|b| b.card(player))
+  }
+
+  /// Check the bingo claim of a player. The first player with a bingo gets the most points.
+  pub fn claim_bingo(&mut self, player: &str) -> Result<(), GameError> {
+    let bingo = self.bingo.as_mut().ok_or(InvalidState(self.status))?;
+    let already_won = bingo.winners_contain(player);
+    let place = bingo.claim(player).ok_or(AnswerNotAllowed("No bingo"))?;
+    if already_won {
+      return Ok(());
+    }
+    log::info!("User {} has bingo (place {})", player, place + 1);
+    if !self.players.iter().any(|score| score.player == player) {
+      self.players.push(PlayerScoreAPI::new(player.to_string()));
+    }
+    let score = self.players
+      .iter_mut()
+      .find(|score| score.player == player)
+      .expect("Player must be in Vector");
+    let points = (MAX_POINTS_CORRECT_ANSWER - place as i32 * 20).max(MIN_POINTS_CORRECT_ANSWER);
+    score.correct += 1;
+    score.last_points = Some(points);
+    score.points += points;
+    self.players.sort_by_key(|p| std::cmp::Reverse(p.points));
+    Ok(())
+  }
+
+  /// Get the state to be sent to clients. Typed answers are hidden until the question is finished, so they can not
+  /// be copied by other players.
+  pub fn exposed(&self) -> GameState {
+    let mut state = self.clone();
+    let pending = matches!(self.status, AppStatus::InGameAnswerPending | AppStatus::InGameBuzzerOpen
+      | AppStatus::InGameBuzzed);
+    let typed = self.current_question.as_ref()
+      .is_some_and(|q| matches!(q.kind, AnswerKind::Text | AnswerKind::Number { .. } | AnswerKind::Parts));
+    if pending && typed {
+      for answer in &mut state.given_answers {
+        answer.answer_id = String::new();
+      }
+    }
+    state
+  }
+
+  pub fn status(&self) -> AppStatus {
+    self.status
+  }
+
+  /// A player buzzes to answer the current question alone, the time to answer is paused
+  pub fn buzz(&mut self, buzz: BuzzFromUser) -> Result<(), GameError> {
+    if self.status != AppStatus::InGameBuzzerOpen {
       return Err(InvalidState(self.status));
     }
+    if self.locked_out.contains(&buzz.user) {
+      return Err(AnswerNotAllowed("Locked out after a wrong answer"));
+    }
+    let now = get_epoch_ms();
+    if now >= self.next_action {
+      return Err(AnswerNotAllowed("Time to answer is over"));
+    }
+    log::info!("User {} buzzed", buzz.user);
+    self.remaining = self.next_action.saturating_sub(now);
+    self.buzzer = Some(buzz.user);
+    self.status = AppStatus::InGameBuzzed;
+    self.action_start = now;
+    self.next_action = now + BUZZER_ANSWER_MS;
+    Ok(())
+  }
+
+  /// Lock out the player who buzzed and continue the question for the others. `played` is how long the song was
+  /// played before, clients continue there.
+  fn resume_after_buzz(&mut self, played: Duration) {
+    if let Some(player) = self.buzzer.take() {
+      log::info!("User {} is locked out", player);
+      self.locked_out.push(player);
+    }
+    let now = get_epoch_ms();
+    self.status = AppStatus::InGameBuzzerOpen;
+    self.action_start = now;
+    self.next_action = now + self.remaining;
+    if let Some(audio) = &mut self.audio {
+      audio.start_time = now;
+      audio.position = played.as_millis() as u64;
+    }
+  }
+
+  /// Receive an answer from a user. In buzzer mode, only the player who buzzed may answer.
+  pub fn give_answer(&mut self, answer: AnswerFromUser) -> Result<(), GameError> {
+    match self.status {
+      AppStatus::InGameAnswerPending => {}
+      AppStatus::InGameBuzzed if self.buzzer.as_ref() == Some(&answer.user) => {}
+      AppStatus::InGameBuzzed => return Err(AnswerNotAllowed("Another player has buzzed")),
+      status => return Err(InvalidState(status)),
+    }
     // Check if answers already contain user somewhere
     if let Some(current_question) = &mut self.current_question {
       let user_has_selected = self.given_answers
@@ -215,13 +684,50 @@ impl GameState {
 
       // Select answer with given ID
       log::info!("Answer with ID {}", answer.id);
-      let selected_answer = current_question.answers
-        .iter_mut()
-        .find(|a| a.id == answer.id);
+      let selected_answer = match current_question.kind {
+        AnswerKind::Choice => current_question.answers
+          .iter()
+          .find(|a| a.id == answer.id)
+          .map(|a| a.text.clone()),
+        AnswerKind::Number { min, max } => answer.id.parse::<i32>().ok()
+          .filter(|n| (min..=max).contains(n))
+          .map(|n| n.to_string()),
+        AnswerKind::Bingo => return Err(AnswerNotAllowed("No answers in bingo, claim a bingo instead")),
+        AnswerKind::Timeline => {
+          let cards = self.timelines.iter()
+            .find(|t| t.player == answer.user)
+            .map_or(0, |t| t.cards.len());
+          answer.position.filter(|p| *p as usize <= cards).map(|p| p.to_string())
+        }
+        AnswerKind::Order => {
+          let mut ids: Vec<&str> = answer.id.split(',').map(|id| id.trim()).collect();
+          let order = ids.join(",");
+          ids.sort();
+          let mut expected: Vec<&str> = current_question.answers.iter().map(|a| a.id.as_str()).collect();
+          expected.sort();
+          Some(order).filter(|_| ids == expected)
+        }
+        AnswerKind::Text => answer.text.as_ref()
+          .map(|t| t.trim().chars().take(MAX_TEXT_ANSWER_LENGTH).collect::<String>())
+          .filter(|t| !t.is_empty()),
+        AnswerKind::Parts => answer.parts.as_ref()
+          .filter(|given| given.len() == current_question.parts.len())
+          .and_then(|given| given.iter().zip(&current_question.parts)
+            .map(|(g, part)| if g.is_empty() || !part.answers.is_empty() {
+              Some(g.clone()).filter(|g| g.is_empty() || part.answers.iter().any(|a| a.id == *g))
+            } else {
+              Some(g.trim().replace(PART_SEPARATOR, " ").chars().take(MAX_TEXT_ANSWER_LENGTH).collect())
+            })
+            .collect::<Option<Vec<String>>>())
+          .filter(|given| given.iter().any(|g| !g.is_empty()))
+          .map(|given| given.join(PART_SEPARATOR)),
+      };
       if let Some(ans) = selected_answer {
-        log::info!("User {} selected {} at {}", answer.user, ans.text, answer.timestamp);
+        log::info!("User {} selected {} at {}", answer.user, ans, answer.timestamp);
+        // Typed answers are stored as id to be compared with the correct answer
+        let answer_id = if current_question.kind == AnswerKind::Choice { answer.id } else { ans };
         self.given_answers.push(
-          UserAnswerExposed { user: answer.user.clone(), ts: answer.timestamp, answer_id: answer.id });
+          UserAnswerExposed { user: answer.user.clone(), ts: answer.timestamp, answer_id, stage: self.reveal_stage });
       } else {
         return Err(AnswerNotAllowed("Invalid ID"));
       }
@@ -244,20 +750,21 @@ fn get_epoch_ms() -> u64 {
 ///
 /// Init => for each `question` [set question => wait for answer] => show results.
 /// Preferences stay the same for the whole round.
-async fn game_round(state: &Arc<RwLock<GameState>>, rx: &mut mpsc::Receiver<GameCommand>, pref: GamePreferences, spotify: Arc<AuthCodeSpotify>,
-              tx_broadcast: &Sender<Message>) -> Result<(), GameError> {
+async fn game_round<Q: Quiz>(state: &Arc<RwLock<GameState>>, rx: &mut mpsc::Receiver<GameCommand>, pref: GamePreferences,
+                             mut quiz: Q, tx_broadcast: &Sender<Message>) -> Result<(), GameError> {
   // Generate questions to be answered
   let mut s = state.write().await;
   prepare_round(&mut s);
   drop(s);
 
-  let mut quiz = SongQuiz::new(spotify, pref.preview_mode);
-  quiz.generate_questions(pref.rounds,
-                          &pref.selected_playlist.as_ref().ok_or(GameError::RuntimeError("No playlist selected"))?.id,
-                          pref.ask_for_artist,
-                          pref.ask_for_title).await?;
+  quiz.generate_questions(&pref).await?;
 
   let mut s = state.write().await;
+  if pref.bingo_mode {
+    let questions = quiz.get_questions();
+    let played = questions.iter().filter_map(|q| q.correct.clone()).collect();
+    s.bingo = Some(Bingo::new(played, questions.iter().filter_map(|q| q.decoy.clone()).collect()));
+  }
   let next_timeout = countdown_round(&mut s, &pref);
   let _ = tx_broadcast.send(s.deref().into());
   drop(s);
@@ -269,21 +776,63 @@ async fn game_round(state: &Arc<RwLock<GameState>>, rx: &mut mpsc::Receiver<Game
       // Set new question (state is changed first so the user sees the question before the music starts -
       // could also be done the other way around, but then the music may start when users do not see the question yet)
       // todo: start song with volume 0 to buffer, remove preview mp3s
-      let mut s = state.write().await;
-      let next_timeout = set_question(question.clone(), &mut s, &pref);
-      let _ = tx_broadcast.send(s.deref().into());
-      drop(s);
-      if let Err(e) = quiz.begin_question_action(question.index as usize).await {
-        log::warn!("Begin question failed with error: {:?}", e);
-      }
+      let mut next_timeout = 0;
+      let mut stopped = false;
+      // Questions with a picture are answered without music
+      let silent = question.media.is_some();
+      let staged = progressive(&question, &pref);
+      let buzzing = buzzer_question(&question, &pref);
+      for (k, &clip) in question.clips.iter().enumerate() {
+        let audio = if silent {
+          None
+        } else {
+          quiz.question_audio(clip).await
+            .inspect_err(|e| log::warn!("Getting audio of question failed with error: {:?}", e))
+            .unwrap_or(None)
+        };
+        // In a progressive reveal, clients only get the part of the song played in the current stage
+        let (audio, full_audio) = if staged { (stage_audio(audio.as_ref(), 0).await, audio) } else { (audio, None) };
+        let mut s = state.write().await;
+        let clip_end = if k == 0 {
+          next_timeout = set_question(question.clone(), audio, &mut s, &pref);
+          s.action_start + (pref.time_to_answer * 1000) as u64
+        } else {
+          set_clip_audio(audio, k, &mut s, &pref)
+        };
+        let _ = tx_broadcast.send(s.deref().into());
+        drop(s);
+        if staged {
+          if reveal_stages(&mut quiz, clip, full_audio, state, rx, tx_broadcast).await {
+            stopped = true;
+            break;
+          }
+        } else if !silent {
+          if let Err(e) = quiz.begin_question_action(clip, Duration::ZERO).await {
+            log::warn!("Begin question failed with error: {:?}", e);
+          }
+        }
 
-      // Wait for users to answer or stopping game
-      if wait_for_command(rx, GameCommand::StopGame, next_timeout).await {
+        // Wait for users to answer (or the next clip) or stopping game
+        let until = if k + 1 == question.clips.len() { next_timeout } else { clip_end };
+        let stop = if buzzing {
+          buzzer_phase(&mut quiz, clip, &question, state, rx, tx_broadcast, &pref).await
+        } else {
+          wait_for_command(rx, GameCommand::StopGame, until).await
+        };
+        if stop {
+          stopped = true;
+          break;
+        }
+      }
+      if stopped {
         break;
       }
 
-      if let Err(e) = quiz.stop_question_action(question.index as usize).await {
-        log::warn!("End question failed with error: {:?}", e);
+      // Stages are stopped one by one
+      if let (Some(&clip), false) = (question.clips.last(), silent || staged) {
+        if let Err(e) = quiz.stop_question_action(clip).await {
+          log::warn!("End question failed with error: {:?}", e);
+        }
       }
 
       // Evaluate answers
@@ -315,6 +864,9 @@ async fn game_round(state: &Arc<RwLock<GameState>>, rx: &mut mpsc::Receiver<Game
 
 fn prepare_round(s: &mut GameState)  {
   s.players = vec![];
+  s.timelines = vec![];
+  s.bingo = None;
+  s.reveal_stage = None;
   s.current_question = None;
   s.status = AppStatus::Preparing;
   s.action_start = 0;
@@ -331,8 +883,7 @@ fn countdown_round(s: &mut GameState, pref: &GamePreferences) -> u64 {
   s.action_start = now;
   s.next_action = now + (pref.time_before_round * 1000) as u64;
   s.given_answers = vec![];
-  let next_timeout = s.next_action;
-  next_timeout
+  s.next_action
 }
 
 // End the round, will display end results
@@ -342,6 +893,8 @@ fn end_round(s: &mut GameState) {
     score.last_points = None;
   }
   s.current_question = None;
+  s.audio = None;
+  s.question_audio = None;
   s.action_start = 0;
   s.next_action = 0;
   s.status = AppStatus::BetweenRounds;
@@ -351,19 +904,27 @@ fn end_round(s: &mut GameState) {
 fn finish_question(question: &Question, s: &mut GameState, pref: &GamePreferences) -> u64 {
   log::info!("Question no {} / {} finished!", question.index + 1, question.total_questions);
   s.status = AppStatus::InGameWaitForNextQuestion;
+  s.audio = None;
+  s.question_audio = None;
   if let Some(q) = &mut s.current_question {
     // Publish correct index
     q.correct = question.correct.clone();
     q.solution = question.solution.clone();
   }
-  calc_points(s, pref);
-  s.players.sort_by(|a, b| b.points.cmp(&a.points));
+  if let Some(bingo) = &mut s.bingo {
+    bingo.reveal();
+  }
+  if question.kind == AnswerKind::Timeline {
+    place_cards(s, question);
+  } else {
+    calc_points(s, pref);
+  }
+  s.players.sort_by_key(|p| std::cmp::Reverse(p.points));
   let now = s.next_action;
   s.action_start = now;
   s.hide_answers = false;
   s.next_action = now + (pref.time_between_answers * 1000) as u64;
-  let next_timeout = s.next_action;
-  next_timeout
+  s.next_action
 }
 
 /**
@@ -381,58 +942,320 @@ fn calc_points_time(time_needed_for_answer: u64, max_answer_time: u64) -> i32 {
   }
 }
 
+/// Get the share of the points a player gets for an answer (1.0 for a correct answer). Number answers get points
+/// decreasing with the distance to the correct number, order answers for every pair of answers in the right order.
+fn answer_share(question: &Question, answer_id: &str, correct: &str, pref: &GamePreferences) -> f32 {
+  match question.kind {
+    AnswerKind::Choice => if answer_id == correct { 1.0 } else { 0.0 },
+    AnswerKind::Text => {
+      let accepted = std::iter::once(correct).chain(question.alternatives.iter().map(|a| a.as_str()));
+      if accepted.into_iter().any(|c| text_matches(answer_id, c, pref.text_tolerance)) { 1.0 } else { 0.0 }
+    }
+    // Timeline answers depend on the timeline of the player, see `place_cards`, bingo has no answers
+    AnswerKind::Timeline | AnswerKind::Bingo => 0.0,
+    AnswerKind::Number { .. } => match (answer_id.parse::<i32>(), correct.parse::<i32>()) {
+      (Ok(answer), Ok(correct)) =>
+        (1.0 - (answer - correct).abs() as f32 / NUMBER_TOLERANCE as f32).max(0.0),
+      _ => 0.0
+    },
+    AnswerKind::Parts => {
+      let right = answer_id.split(PART_SEPARATOR)
+        .zip(correct.split(PART_SEPARATOR))
+        .zip(&question.parts)
+        .filter(|((answer, correct), part)| if part.answers.is_empty() {
+          std::iter::once(*correct).chain(part.alternatives.iter().map(|a| a.as_str()))
+            .any(|c| text_matches(answer, c, pref.text_tolerance))
+        } else {
+          answer == correct
+        })
+        .count();
+      if right == question.parts.len() {
+        1.0 + PARTS_BONUS
+      } else {
+        right as f32 / question.parts.len() as f32
+      }
+    }
+    AnswerKind::Order => {
+      let answer: Vec<&str> = answer_id.split(',').collect();
+      let correct: Vec<&str> = correct.split(',').collect();
+      let position = |id: &str| answer.iter().position(|a| *a == id);
+      let pairs = correct.len() * correct.len().saturating_sub(1) / 2;
+      let right = (0..correct.len())
+        .flat_map(|i| (i + 1..correct.len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| matches!((position(correct[i]), position(correct[j])), (Some(a), Some(b)) if a < b))
+        .count();
+      if pairs == 0 { 0.0 } else { right as f32 / pairs as f32 }
+    }
+  }
+}
+
 /// Calculate the points for all players for the current question
 fn calc_points(s: &mut GameState, pref: &GamePreferences) {
   if let Some(q) = &s.current_question {
     let given_answers = &mut s.given_answers;
-    given_answers.sort_by(|a, b| a.ts.cmp(&b.ts));
+    given_answers.sort_by_key(|a| a.ts);
     for (pos, user_ans) in given_answers.iter().enumerate() {
       // find player in results
       if !s.players.iter_mut().any(|score| score.player == user_ans.user) {
         s.players.push(PlayerScoreAPI::new(user_ans.user.clone()));
       }
       // Points need to be calculated here, because later s can't be borrowed (since score = mutable borrow)
-      let time_needed_for_answer = user_ans.ts - s.action_start;
+      let time_needed_for_answer = user_ans.ts.saturating_sub(s.question_start);
       let mut points_if_correct: i32 = match pref.scoremode {
-        ScoreMode::TimeLinear => calc_points_time(time_needed_for_answer, s.next_action - s.action_start) as i32,
+        ScoreMode::TimeLinear => calc_points_time(time_needed_for_answer, s.next_action.saturating_sub(s.question_start)),
         ScoreMode::TimeFunction => minterpolate::linear_interpolate(time_needed_for_answer as f32, &POINTS_TIME, &POINTS_AMOUNT, false),
         ScoreMode::Order => min(MIN_POINTS_CORRECT_ANSWER, MAX_POINTS_CORRECT_ANSWER - pos as i32 * 10),
         ScoreMode::WrongFalse => MAX_POINTS_CORRECT_ANSWER
       };
-      points_if_correct = std::cmp::max(MIN_POINTS_CORRECT_ANSWER,
-                                        std::cmp::min(MAX_POINTS_CORRECT_ANSWER, points_if_correct));
+      // Only the player who buzzed first and answered correctly gets points, but no matter how fast
+      if buzzer_question(q, pref) {
+        points_if_correct = MAX_POINTS_CORRECT_ANSWER;
+      }
+      // Every later stage of a progressive reveal gives fewer points
+      if let Some(stage) = user_ans.stage {
+        points_if_correct = MAX_POINTS_CORRECT_ANSWER - stage as i32 * (MAX_POINTS_CORRECT_ANSWER - MIN_POINTS_CORRECT_ANSWER)
+          / (REVEAL_STAGES.len() as i32 - 1);
+      }
+      points_if_correct = points_if_correct.clamp(MIN_POINTS_CORRECT_ANSWER, MAX_POINTS_CORRECT_ANSWER);
       let score = s.players
         .iter_mut()
         .find(|score| score.player == user_ans.user)
         .expect("Player must be in Vector");
       score.answers_given += 1;
       score.last_time = Some(time_needed_for_answer as f32 / 1000.0);
-      if &user_ans.answer_id == q.correct.as_ref().expect("No correct answer in calc_points") {
+      let correct = q.correct.as_ref().expect("No correct answer in calc_points");
+      let share = answer_share(q, &user_ans.answer_id, correct, pref);
+      if share >= 1.0 {
         score.correct += 1;
-        score.last_points = Some(points_if_correct);
-        score.points += points_if_correct;
-      } else {
-        score.last_points = Some(0);
       }
+      let points = (points_if_correct as f32 * share).round() as i32;
+      score.last_points = Some(points);
+      score.points += points;
+    }
+  }
+}
+
+/// Insert the song of a timeline question into the timelines of all players who placed it correctly.
+/// Every card is worth one point.
+fn place_cards(s: &mut GameState, question: &Question) {
+  let Some(year) = question.correct.as_ref().and_then(|y| y.parse::<i32>().ok()) else { return };
+  let song = question.solution.clone().unwrap_or_default();
+  for user_ans in &s.given_answers {
+    if !s.players.iter().any(|score| score.player == user_ans.user) {
+      s.players.push(PlayerScoreAPI::new(user_ans.user.clone()));
+    }
+    if !s.timelines.iter().any(|t| t.player == user_ans.user) {
+      s.timelines.push(PlayerTimeline { player: user_ans.user.clone(), cards: vec![] });
+    }
+    let timeline = s.timelines
+      .iter_mut()
+      .find(|t| t.player == user_ans.user)
+      .expect("Player must have a timeline");
+    let score = s.players
+      .iter_mut()
+      .find(|score| score.player == user_ans.user)
+      .expect("Player must be in Vector");
+    score.answers_given += 1;
+    score.last_time = Some(user_ans.ts.saturating_sub(s.question_start) as f32 / 1000.0);
+    let position = user_ans.answer_id.parse::<usize>().unwrap_or(usize::MAX);
+    if timeline.fits(position, year) {
+      timeline.cards.insert(position, TimelineCard { song: song.clone(), year });
+      score.correct += 1;
+      score.last_points = Some(1);
+      score.points = timeline.cards.len() as i32;
+    } else {
+      score.last_points = Some(0);
     }
   }
 }
 
 /// Set the current question to be answered
-fn set_question(mut question: Question, s: &mut GameState, pref: &GamePreferences) -> u64 {
+fn set_question(mut question: Question, audio: Option<QuestionAudio>, s: &mut GameState, pref: &GamePreferences) -> u64 {
   log::info!("Question no {} / {}", question.index + 1, question.total_questions);
+  if let (Some(bingo), Some(entry)) = (&mut s.bingo, &question.correct) {
+    bingo.play(entry.clone());
+  }
   question.correct = None;
   question.solution = None;
-  s.current_question = Some(question);
   let now = s.next_action;
+  // Every clip is played for the time to answer
+  let clips = question.clips.len().max(1) as u64;
+  let staged = progressive(&question, pref);
+  s.current_question = Some(question);
   s.action_start = now;
-  s.next_action = now + (pref.time_to_answer * 1000) as u64;
-  s.status = AppStatus::InGameAnswerPending;
+  s.question_start = now;
+  set_clip_audio(audio, 0, s, pref);
+  s.reveal_stage = None;
+  s.next_action = if staged {
+    set_reveal_stage(0, s);
+    // After the last stage, there is the usual time to answer
+    let last = REVEAL_STAGES.len() - 1;
+    now + stage_start(last) + REVEAL_STAGES[last] * 1000 + (pref.time_to_answer * 1000) as u64
+  } else {
+    now + clips * (pref.time_to_answer * 1000) as u64
+  };
+  s.status = if buzzer_question(s.current_question.as_ref().expect("Question has just been set"), pref) {
+    AppStatus::InGameBuzzerOpen
+  } else {
+    AppStatus::InGameAnswerPending
+  };
+  s.buzzer = None;
+  s.locked_out = vec![];
   s.given_answers = vec![];
-  s.hide_answers = if pref.hide_answers {true} else {false};
+  s.hide_answers = pref.hide_answers;
   s.next_action
 }
 
+/// Set the audio of a clip of the current question, returns the time at which the clip ends
+fn set_clip_audio(audio: Option<QuestionAudio>, clip: usize, s: &mut GameState, pref: &GamePreferences) -> u64 {
+  let start = s.action_start + clip as u64 * (pref.time_to_answer * 1000) as u64;
+  let index = s.current_question.as_ref().map(|q| q.index).unwrap_or_default();
+  s.audio = audio.as_ref().map(|_| AudioInfo {
+    url: format!("/question_audio?index={}&clip={}", index, clip),
+    start_time: start,
+    position: 0,
+    length: None,
+  });
+  s.question_audio = audio;
+  start + (pref.time_to_answer * 1000) as u64
+}
+
+/// Check if the song of a question is revealed in stages, this is not possible for several clips and without audio
+fn progressive(question: &Question, pref: &GamePreferences) -> bool {
+  pref.progressive_reveal && question.clips.len() == 1 && question.media.is_none() && !pref.buzzer_mode
+}
+
+/// Longest time a clip of a question is played: the time to answer, for a progressive reveal at least the last stage
+pub fn clip_length(pref: &GamePreferences) -> Duration {
+  let last_stage = REVEAL_STAGES[REVEAL_STAGES.len() - 1];
+  Duration::from_secs((pref.time_to_answer as u64).max(if pref.progressive_reveal { last_stage } else { 0 }))
+}
+
+/// Check if players buzz to answer a question. Not used for several clips, cover questions (the cover gets sharper
+/// over the whole time to answer, which a buzz would interrupt) and in timeline and bingo mode.
+fn buzzer_question(question: &Question, pref: &GamePreferences) -> bool {
+  pref.buzzer_mode && question.clips.len() == 1 && question.media.is_none() && !pref.timeline_mode && !pref.bingo_mode
+}
+
+/// Time of a stage of the progressive reveal (ms after the start of the question)
+fn stage_start(stage: usize) -> u64 {
+  REVEAL_STAGES[..stage].iter().map(|s| s * 1000 + REVEAL_PAUSE_MS).sum()
+}
+
+/// Set the stage of the progressive reveal, the audio of the question has to be the part of the stage
+fn set_reveal_stage(stage: usize, s: &mut GameState) {
+  let start = s.action_start + stage_start(stage);
+  let index = s.current_question.as_ref().map(|q| q.index).unwrap_or_default();
+  s.reveal_stage = Some(stage as u32);
+  if let Some(audio) = &mut s.audio {
+    // Every stage has its own clip, so clients do not keep a shorter one
+    audio.url = format!("/question_audio?index={}&clip=0&stage={}", index, stage);
+    audio.start_time = start;
+    audio.length = Some(REVEAL_STAGES[stage] * 1000);
+  }
+}
+
+/// Cut the part of the audio of a question that is played in a stage of the progressive reveal
+async fn stage_audio(audio: Option<&QuestionAudio>, stage: usize) -> Option<QuestionAudio> {
+  player::clip(audio?.data.clone(), Duration::ZERO, Duration::from_secs(REVEAL_STAGES[stage])).await
+    .inspect_err(|e| log::warn!("Cutting audio of reveal stage failed with error: {:?}", e))
+    .ok()
+}
+
+/// Play the song of the current question in stages of increasing length, every stage starts at the beginning of the
+/// snippet. Players may answer in any stage, clients get the part of `full_audio` of the current stage only. Returns
+/// true if the game has been stopped.
+async fn reveal_stages<Q: Quiz>(quiz: &mut Q, clip: usize, full_audio: Option<QuestionAudio>,
+                                state: &Arc<RwLock<GameState>>, rx: &mut mpsc::Receiver<GameCommand>,
+                                tx_broadcast: &Sender<Message>) -> bool {
+  let question_start = state.read().await.action_start;
+  for (stage, seconds) in REVEAL_STAGES.iter().enumerate() {
+    let begin = question_start + stage_start(stage);
+    if stage > 0 {
+      let audio = stage_audio(full_audio.as_ref(), stage).await;
+      if wait_for_command(rx, GameCommand::StopGame, begin).await {
+        return true;
+      }
+      let mut s = state.write().await;
+      if audio.is_none() {
+        s.audio = None;
+      }
+      s.question_audio = audio;
+      set_reveal_stage(stage, &mut s);
+      let _ = tx_broadcast.send(s.deref().into());
+      drop(s);
+    }
+    log::info!("Reveal stage {} ({} s)", stage + 1, seconds);
+    if let Err(e) = quiz.begin_question_action(clip, Duration::ZERO).await {
+      log::warn!("Begin question failed with error: {:?}", e);
+    }
+    // Starting the playback may take a moment (e.g. requests to Spotify), the stage is measured from then on
+    let playing = get_epoch_ms();
+    if wait_for_command(rx, GameCommand::StopGame, playing + seconds * 1000).await {
+      return true;
+    }
+    if let Err(e) = quiz.cut_question_action(clip).await {
+      log::warn!("End question failed with error: {:?}", e);
+    }
+  }
+  false
+}
+
+/// Wait for the end of a question in buzzer mode. The first player to buzz pauses the song and answers alone. A wrong
+/// answer (or none in time) locks the player out and the song continues for the others, a correct one ends the
+/// question. Returns true if the game has been stopped.
+async fn buzzer_phase<Q: Quiz>(quiz: &mut Q, clip: usize, question: &Question, state: &Arc<RwLock<GameState>>,
+                               rx: &mut mpsc::Receiver<GameCommand>, tx_broadcast: &Sender<Message>,
+                               pref: &GamePreferences) -> bool {
+  // How long the song has been played before the last buzz and when it has been started again
+  let mut played = Duration::ZERO;
+  let mut resumed = state.read().await.action_start;
+  loop {
+    let until = state.read().await.next_action;
+    let command = next_command(rx, until).await;
+    let mut s = state.write().await;
+    match command {
+      Some(GameCommand::StopGame) => return true,
+      Some(GameCommand::Buzz) if s.status == AppStatus::InGameBuzzed => {
+        played += Duration::from_millis(s.action_start.saturating_sub(resumed));
+        drop(s);
+        if let Err(e) = quiz.stop_question_action(clip).await {
+          log::warn!("Pausing question failed with error: {:?}", e);
+        }
+      }
+      Some(GameCommand::BuzzerAnswered) | None if s.status == AppStatus::InGameBuzzed => {
+        let correct = question.correct.as_deref().unwrap_or_default();
+        let right = s.buzzer.as_ref()
+          .and_then(|buzzer| s.given_answers.iter().find(|a| &a.user == buzzer))
+          .is_some_and(|a| answer_share(question, &a.answer_id, correct, pref) >= 1.0);
+        // The question ends now if the answer is right or there is no time left for the others
+        if right || s.remaining == 0 {
+          s.next_action = get_epoch_ms();
+          return false;
+        }
+        s.resume_after_buzz(played);
+        resumed = s.action_start;
+        let _ = tx_broadcast.send(s.deref().into());
+        drop(s);
+        if let Err(e) = quiz.begin_question_action(clip, played).await {
+          log::warn!("Resuming question failed with error: {:?}", e);
+        }
+      }
+      None => return false,
+      _ => {}
+    }
+  }
+}
+
+/// Wait for the next command until some time in ms after epoch, `None` if there is none until then
+async fn next_command(rx: &mut mpsc::Receiver<GameCommand>, until: u64) -> Option<GameCommand> {
+  let diff = until.saturating_sub(get_epoch_ms());
+  if diff == 0 {
+    return None;
+  }
+  tokio::time::timeout(Duration::from_millis(diff), rx.recv()).await.ok().flatten()
+}
+
 /// Wait for a command or until some time in ms after epoch
 async fn wait_for_command(rx: &mut mpsc::Receiver<GameCommand>, command: GameCommand, until: u64) -> bool {
   loop {
@@ -479,13 +1302,44 @@ pub async fn run(state: Arc<RwLock<GameState>>, mut rx: mpsc::Receiver<GameComma
     let pref = p_mut.clone();
     drop(p_mut);
 
-    // Get spotify auth code
+    // Select the song source from the first playlist of the pool and play one round
+    let source = pref.pool().first().and_then(|p| QuizSource::from_playlist_id(&p.playlist.id));
     let r_mut = references.lock().await;
-    let spotify = r_mut.spotify_client.clone();
-    drop(r_mut);
-
-    // Play one round
-    match game_round(&state, &mut rx, pref, spotify, &tx_broadcast).await {
+    let result = match source {
+      Some(QuizSource::Spotify) => {
+        let quiz = SongQuiz::new(r_mut.spotify_client.clone(), r_mut.track_cache.clone(), r_mut.player.clone(),
+                                pref.preview_mode);
+        drop(r_mut);
+        game_round(&state, &mut rx, pref, quiz, &tx_broadcast).await
+      }
+      Some(QuizSource::Local) => {
+        let quiz = LocalQuiz::new(r_mut.music_dir.clone(), r_mut.playlist_dir.clone(), r_mut.player.clone());
+        drop(r_mut);
+        game_round(&state, &mut rx, pref, quiz, &tx_broadcast).await
+      }
+      Some(QuizSource::Subsonic) => {
+        let client = r_mut.subsonic_client.clone();
+        let player = r_mut.player.clone();
+        drop(r_mut);
+        match client {
+          Some(c) => game_round(&state, &mut rx, pref, SubsonicQuiz::new(c, player), &tx_broadcast).await,
+          None => Err(GameError::RuntimeError("No subsonic server configured"))
+        }
+      }
+      Some(QuizSource::Mpd) => {
+        let client = r_mut.mpd_client.clone();
+        drop(r_mut);
+        match client {
+          Some(c) => game_round(&state, &mut rx, pref, MpdQuiz::new(c), &tx_broadcast).await,
+          None => Err(GameError::RuntimeError("No MPD configured"))
+        }
+      }
+      None => {
+        drop(r_mut);
+        Err(GameError::RuntimeError("No playlist selected or playlist source unknown"))
+      }
+    };
+    match result {
       Ok(()) => log::info!("Round ended"),
       Err(e) => log::warn!("Round ended with error: {:?}", e)
     }
@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use lofty::prelude::*;
+use lofty::tag::ItemKey;
+use crate::game::{clip_length, GamePreferences, Playlist, Question};
+use crate::player::{clip, AudioPlayer};
+use crate::playlist_file;
+use crate::playlist_file::PLAYLIST_FILE_PREFIX;
+use crate::quiz::{create_questions, pool_playlists, Quiz, QuizError, QuizSource, QuestionAudio, SongQuestion, Track, TrackPool,
+                  FADE_OUT_TIME};
+
+/// Prefix of playlist ids that belong to the local music library
+pub const LOCAL_PREFIX: &str = "local:";
+
+/// File extensions that are considered as songs when scanning the library
+const AUDIO_EXTENSIONS: [&str; 3] = ["mp3", "flac", "ogg"];
+
+/// Get the playlists offered by the music library in `dir`: The whole library and every direct subdirectory
+pub fn find_playlists(dir: &Path) -> Vec<Playlist> {
+  let mut playlists = vec![Playlist { name: "Lokale Bibliothek".to_string(), id: playlist_id(dir) }];
+  match fs::read_dir(dir) {
+    Ok(entries) => {
+      let mut subdirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+      subdirs.sort();
+      playlists.extend(subdirs.iter().map(|path| Playlist {
+        name: format!("Lokal: {}", path.file_name().unwrap_or_default().to_string_lossy()),
+        id: playlist_id(path),
+      }));
+    }
+    Err(e) => log::warn!("Could not read music directory {:?}: {}", dir, e)
+  }
+  playlists
+}
+
+fn playlist_id(dir: &Path) -> String {
+  format!("{}{}", LOCAL_PREFIX, dir.to_string_lossy())
+}
+
+/// Recursively collects all songs in `dir` that have at least a title and an artist tag
+fn scan_directory(dir: &Path, tracks: &mut Vec<Track>) -> Result<(), QuizError> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.is_dir() {
+      scan_directory(&path, tracks)?;
+    } else if is_audio_file(&path) {
+      match read_track(&path) {
+        Some(track) => tracks.push(track),
+        None => log::debug!("Skipped {:?}, title or artist missing", path)
+      }
+    }
+  }
+  Ok(())
+}
+
+fn is_audio_file(path: &Path) -> bool {
+  path.extension()
+    .map(|ext| ext.to_string_lossy().to_lowercase())
+    .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Read the tags of a song file
+pub fn read_track(path: &Path) -> Option<Track> {
+  let file = lofty::read_from_path(path)
+    .inspect_err(|e| log::warn!("Could not read tags of {:?}: {}", path, e))
+    .ok()?;
+  let tag = file.primary_tag().or(file.first_tag())?;
+  // Tags may contain an entry for every artist
+  let artists: Vec<String> = tag.get_strings(ItemKey::TrackArtist)
+    .filter(|a| !a.is_empty())
+    .map(|a| a.to_string())
+    .collect();
+  if artists.is_empty() {
+    return None;
+  }
+  Some(Track {
+    id: path.to_string_lossy().to_string(),
+    title: tag.title()?.to_string(),
+    artists,
+    album: tag.album().map(|a| a.to_string()),
+    year: tag.date().map(|d| d.year as i32),
+    duration: chrono::Duration::from_std(file.properties().duration()).unwrap_or_default(),
+    preview_url: None,
+    popularity: None,
+    cover_url: None,
+  })
+}
+
+/// Check that `path` is located in `root`, so clients can not choose arbitrary files on the host
+pub fn ensure_inside(path: &Path, root: Option<&PathBuf>) -> Result<PathBuf, QuizError> {
+  let path = path.canonicalize()?;
+  match root {
+    Some(root) if path.starts_with(root.canonicalize()?) => Ok(path),
+    _ => Err(QuizError::RuntimeError(format!("{:?} is not part of the configured directories", path)))
+  }
+}
+
+/// Quiz using song files on the host, either from a directory of the library or from a playlist file
+pub struct LocalQuiz {
+  // directory containing all songs, only directories in there may be played
+  library: Option<PathBuf>,
+  // directory containing playlist files, only playlist files in there may be played
+  playlist_dir: Option<PathBuf>,
+  songs: Vec<SongQuestion>,
+  questions: Vec<Question>,
+  player: AudioPlayer,
+  // clip of the current question
+  clip: Option<(usize, QuestionAudio)>,
+  clip_length: Duration,
+}
+
+impl LocalQuiz {
+  pub fn new(library: Option<PathBuf>, playlist_dir: Option<PathBuf>, player: AudioPlayer) -> LocalQuiz {
+    LocalQuiz {
+      library,
+      playlist_dir,
+      songs: vec![],
+      questions: vec![],
+      player,
+      clip: None,
+      clip_length: Duration::ZERO,
+    }
+  }
+
+  /// Get the songs of a library directory or playlist file
+  async fn get_tracks(&self, playlist_id: &str) -> Result<Vec<Track>, QuizError> {
+    let tracks = if let Some(dir) = playlist_id.strip_prefix(LOCAL_PREFIX) {
+      let dir = ensure_inside(Path::new(dir), self.library.as_ref())?;
+      // Reading all tags may take a while for big libraries
+      tokio::task::spawn_blocking(move || -> Result<Vec<Track>, QuizError> {
+        let mut tracks = vec![];
+        scan_directory(&dir, &mut tracks)?;
+        Ok(tracks)
+      }).await.map_err(|e| QuizError::RuntimeError(e.to_string()))??
+    } else if let Some(file) = playlist_id.strip_prefix(PLAYLIST_FILE_PREFIX) {
+      let file = ensure_inside(Path::new(file), self.playlist_dir.as_ref())?;
+      // Songs of playlist files are served to clients, so they have to be in one of the configured directories too
+      let roots: Vec<PathBuf> = self.library.iter().chain(self.playlist_dir.iter()).cloned().collect();
+      tokio::task::spawn_blocking(move || playlist_file::read_tracks(&file, &roots))
+        .await.map_err(|e| QuizError::RuntimeError(e.to_string()))??
+    } else {
+      return Err(QuizError::RuntimeError(format!("{} is no local playlist", playlist_id)));
+    };
+    log::info!("Found {} songs in local playlist {}", tracks.len(), playlist_id);
+    Ok(tracks)
+  }
+}
+
+impl Quiz for LocalQuiz {
+  async fn generate_questions(&mut self, pref: &GamePreferences) -> Result<(), QuizError> {
+    let mut pools = vec![];
+    for p in pool_playlists(pref, QuizSource::Local)? {
+      pools.push(TrackPool { tracks: self.get_tracks(&p.playlist.id).await?, weight: p.weight });
+    }
+
+    let (songs, questions) = create_questions(pools, pref)?;
+    self.songs = songs;
+    self.questions = questions;
+    self.clip_length = clip_length(pref);
+    Ok(())
+  }
+
+  /// Plays the song file belonging to the question given by `index` from the start of its snippet
+  async fn begin_question_action(&mut self, index: usize, offset: Duration) -> Result<(), QuizError> {
+    if let Some(audio) = self.question_audio(index).await? {
+      self.player.play(audio.data, offset).await?;
+    }
+    let song = &self.songs[index].song;
+    log::info!("Begin question {} {} - {} ({}, {})", index, song.artist_credit(", "), song.title,
+      song.album.as_deref().unwrap_or("unknown album"), song.year.map(|y| y.to_string()).unwrap_or_default());
+    Ok(())
+  }
+
+  async fn stop_question_action(&mut self, index: usize) -> Result<(), QuizError> {
+    let song = &self.songs.get(index).ok_or(QuizError::RuntimeError("Invalid song index".to_string()))?.song;
+    self.player.fade(FADE_OUT_TIME)?;
+    log::info!("End question {} {} - {}", index, song.artist_credit(", "), song.title);
+    Ok(())
+  }
+
+  async fn cut_question_action(&mut self, _index: usize) -> Result<(), QuizError> {
+    self.player.stop()
+  }
+
+  fn get_questions(&self) -> &Vec<Question> {
+    &self.questions
+  }
+
+  /// Reads the song file and cuts the clip of the question, only the clip of the current question is kept
+  async fn question_audio(&mut self, index: usize) -> Result<Option<QuestionAudio>, QuizError> {
+    if let Some((_, audio)) = self.clip.as_ref().filter(|(i, _)| *i == index) {
+      return Ok(Some(audio.clone()));
+    }
+    let song = self.songs.get(index).ok_or(QuizError::RuntimeError("Invalid song index".to_string()))?;
+    let data = tokio::fs::read(&song.song.id).await?.into();
+    let audio = clip(data, song.start.to_std().unwrap_or_default(), self.clip_length).await?;
+    self.clip = Some((index, audio.clone()));
+    Ok(Some(audio))
+  }
+
+  async fn shutdown(&mut self) -> Result<(), QuizError> {
+    self.player.stop()
+  }
+}
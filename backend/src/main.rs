@@ -1,5 +1,6 @@
 use std::{fs};
 use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::{Arc};
 use tokio::sync::{Mutex, RwLock, mpsc};
@@ -8,19 +9,33 @@ use clap::Parser;
 use log::LevelFilter;
 use rspotify::{AuthCodeSpotify, Config, Credentials, OAuth};
 use rspotify::clients::{BaseClient, OAuthClient};
-use serde_json;
 use simple_logger::SimpleLogger;
 use tower_http::services::ServeDir;
 use crate::communication::*;
 use crate::game::{GameCommand, GamePreferences, GameReferences, GameState};
+use crate::player::AudioPlayer;
+use crate::mpd::MpdClient;
 use crate::spotify::spotify_loop;
+use crate::subsonic::{SubsonicClient, SubsonicPrefs};
+use crate::track_cache::TrackCache;
 
+mod bingo;
 mod game;
 mod quiz;
 mod spotify;
 mod communication;
-
-const PREFERENCES_FILE: &'static str = "preferences.json";
+mod local;
+mod matching;
+mod player;
+mod playlist_file;
+mod subsonic;
+mod titles;
+mod mpd;
+mod track_cache;
+
+const PREFERENCES_FILE: &str = "preferences.json";
+/// Start positions of single songs, see `quiz::load_snippet_overrides`
+const SNIPPETS_FILE: &str = "snippets.json";
 
 // Setup the command line interface with clap.
 #[derive(Parser, Debug)]
@@ -45,6 +60,30 @@ struct Opt {
   /// set the directory where static files are to be found
   #[clap(long = "static-dir", default_value = "../dist")]
   static_dir: String,
+
+  /// set the directory of a local music library (mp3, flac, ogg) to be used as song source
+  #[clap(short = 'm', long = "music-dir")]
+  music_dir: Option<PathBuf>,
+
+  /// set the config file of a subsonic compatible server (e.g. Navidrome) to be used as song source
+  #[clap(long = "subsonic", default_value = "subsonic.json")]
+  subsonic_json: String,
+
+  /// set the address (host:port) of a music player daemon to be used as song source and player
+  #[clap(long = "mpd")]
+  mpd_addr: Option<String>,
+
+  /// set the password of the music player daemon
+  #[clap(long = "mpd-password")]
+  mpd_password: Option<String>,
+
+  /// set the directory containing playlist files (m3u, m3u8, pls, xspf) referencing local songs
+  #[clap(long = "playlist-dir")]
+  playlist_dir: Option<PathBuf>,
+
+  /// set the directory where the tracks of Spotify playlists are cached
+  #[clap(long = "cache-dir", default_value = "cache")]
+  cache_dir: PathBuf,
 }
 
 #[tokio::main]
@@ -97,10 +136,31 @@ async fn main() {
     Err(e) => log::warn!("Could not load token: {:?}", e)
   }
 
+  // Read subsonic server settings, the server is optional
+  let subsonic_client = match fs::File::open(&opt.subsonic_json) {
+    Ok(file) => match serde_json::from_reader::<fs::File, SubsonicPrefs>(file) {
+      Ok(s) => {
+        log::info!("Successfully read file {}, using subsonic server {}", &opt.subsonic_json, s.url);
+        Some(Arc::new(SubsonicClient::new(s)))
+      }
+      Err(e) => {
+        log::warn!("File {} not in expected format ({}), not using subsonic", &opt.subsonic_json, e);
+        None
+      }
+    },
+    Err(_) => {
+      log::info!("Did not find {}, not using subsonic", &opt.subsonic_json);
+      None
+    }
+  };
+
   // Shared objects
   let spotify_arc = Arc::new(spotify_client);
   let references = Arc::new(Mutex::new(
-    GameReferences { tx_commands: tx_cmd, tx_spotify, spotify_client: spotify_arc, tx_broadcast, rx_broadcast }));
+    GameReferences { tx_commands: tx_cmd, tx_spotify, spotify_client: spotify_arc, tx_broadcast, rx_broadcast,
+                     music_dir: opt.music_dir, playlist_dir: opt.playlist_dir, subsonic_client,
+                     mpd_client: opt.mpd_addr.map(|addr| MpdClient::new(addr, opt.mpd_password)), player: AudioPlayer::spawn(),
+                     track_cache: TrackCache::new(opt.cache_dir) }));
   let mut game_pref = GamePreferences::new();
   if let Ok(file) = fs::File::open(PREFERENCES_FILE) {
     if let Ok(p) = serde_json::from_reader::<fs::File, GamePreferences>(file) {
@@ -130,6 +190,9 @@ async fn main() {
     .route("/get_state", get(get_state))
     .route("/get_time", get(get_time))
     .route("/get_preferences", get(get_preferences))
+    .route("/question_audio", get(get_question_audio))
+    .route("/bingo_card", get(get_bingo_card))
+    .route("/claim_bingo", post(claim_bingo))
     .route("/stop_game", post(stop_game))
     .route("/start_game", post(start_game))
     .route("/press_button", post(select_answer))
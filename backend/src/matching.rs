@@ -0,0 +1,73 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Normalize a text for comparison: lowercase, without accents, punctuation and a leading "the"
+pub fn normalize(text: &str) -> String {
+  let text: String = text
+    .nfd()
+    .filter(|c| !is_combining_mark(*c))
+    .flat_map(|c| c.to_lowercase())
+    .map(|c| if c == '&' { '+' } else { c })
+    .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '+')
+    .collect::<String>()
+    .replace('+', " and ")
+    .replace('ß', "ss");
+  let words: Vec<&str> = text.split_whitespace().collect();
+  match words.split_first() {
+    Some((&"the", rest)) if !rest.is_empty() => rest.join(" "),
+    _ => words.join(" ")
+  }
+}
+
+/// Check if a typed answer matches the correct one. After normalization, `tolerance` percent of the characters of the
+/// correct answer may be wrong (edit distance), 0 only accepts exact matches.
+pub fn text_matches(answer: &str, correct: &str, tolerance: u32) -> bool {
+  let answer = normalize(answer);
+  let correct = normalize(correct);
+  if answer.is_empty() {
+    return false;
+  }
+  let allowed = correct.chars().count() * tolerance.min(100) as usize / 100;
+  strsim::levenshtein(&answer, &correct) <= allowed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn normalized_texts() {
+    assert_eq!(normalize("Beyoncé"), "beyonce");
+    assert_eq!(normalize("  The  Beatles "), "beatles");
+    assert_eq!(normalize("The"), "the");
+    assert_eq!(normalize("Simon & Garfunkel"), "simon and garfunkel");
+    assert_eq!(normalize("Straße"), "strasse");
+    assert_eq!(normalize("Don't Stop Me Now!"), "dont stop me now");
+  }
+
+  #[test]
+  fn accents_case_and_articles_are_ignored() {
+    assert!(text_matches("beyonce", "Beyoncé", 0));
+    assert!(text_matches("Beatles", "The Beatles", 0));
+    assert!(text_matches("the beatles", "Beatles", 0));
+    assert!(text_matches("simon and garfunkel", "Simon & Garfunkel", 0));
+  }
+
+  #[test]
+  fn typos_within_tolerance() {
+    // 20 % of "bohemian rhapsody" (17 characters) allow 3 wrong ones
+    assert!(text_matches("Bohemian Rapsody", "Bohemian Rhapsody", 20));
+    assert!(!text_matches("Bohemian Rapsody", "Bohemian Rhapsody", 0));
+    assert!(!text_matches("Bohemn Rapsdy", "Bohemian Rhapsody", 20));
+    // Short answers need to match exactly, 20 % of 4 characters are rounded down to 0
+    assert!(text_matches("Help", "Help!", 20));
+    assert!(!text_matches("Halp", "Help", 20));
+  }
+
+  #[test]
+  fn empty_answers_are_rejected() {
+    assert!(!text_matches("", "ABBA", 100));
+    assert!(!text_matches(" !? ", "ABBA", 100));
+    assert!(!text_matches("", "", 0));
+  }
+}
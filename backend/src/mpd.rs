@@ -0,0 +1,229 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use crate::game::{GamePreferences, Playlist, Question};
+use crate::quiz::{create_questions, pool_playlists, Quiz, QuizError, QuizSource, SongQuestion, Track, TrackPool};
+
+/// Prefix of playlist ids that belong to MPD
+pub const MPD_PREFIX: &str = "mpd:";
+/// Playlist id of the whole MPD database
+const DATABASE_ID: &str = "mpd:database";
+/// Prefix of playlist ids that belong to stored playlists of MPD
+const STORED_PLAYLIST_PREFIX: &str = "mpd:playlist:";
+/// Time to wait for MPD to accept a connection or to send the next line of an answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connection settings for a Music Player Daemon
+#[derive(Clone)]
+pub struct MpdClient {
+  addr: String,
+  password: Option<String>,
+}
+
+/// Open connection to MPD, see https://mpd.readthedocs.io/en/latest/protocol.html
+struct MpdConnection {
+  reader: BufReader<OwnedReadHalf>,
+  writer: OwnedWriteHalf,
+}
+
+impl MpdClient {
+  pub fn new(addr: String, password: Option<String>) -> MpdClient {
+    MpdClient { addr, password }
+  }
+
+  /// Connect to MPD. MPD closes idle connections, so a new connection is used for every action.
+  async fn connect(&self) -> Result<MpdConnection, QuizError> {
+    let stream = with_timeout(TcpStream::connect(&self.addr)).await?;
+    let (read, writer) = stream.into_split();
+    let mut connection = MpdConnection { reader: BufReader::new(read), writer };
+    let mut greeting = String::new();
+    with_timeout(connection.reader.read_line(&mut greeting)).await?;
+    if !greeting.starts_with("OK MPD") {
+      return Err(QuizError::MpdError(format!("Unexpected greeting: {}", greeting.trim())));
+    }
+    if let Some(password) = &self.password {
+      connection.command(&format!("password {}", quote(password))).await?;
+    }
+    Ok(connection)
+  }
+
+  /// Get the stored playlists and the whole database as playlist
+  pub async fn playlists(&self) -> Result<Vec<Playlist>, QuizError> {
+    let mut connection = self.connect().await?;
+    let mut playlists = vec![Playlist { name: "MPD Datenbank".to_string(), id: DATABASE_ID.to_string() }];
+    playlists.extend(connection.command("listplaylists").await?
+      .into_iter()
+      .filter(|(key, _)| key == "playlist")
+      .map(|(_, name)| Playlist { name: format!("MPD: {}", name), id: format!("{}{}", STORED_PLAYLIST_PREFIX, name) }));
+    Ok(playlists)
+  }
+
+  /// Get all songs of the playlist with the given id that have a title and an artist
+  pub async fn tracks(&self, playlist_id: &str) -> Result<Vec<Track>, QuizError> {
+    let command = if playlist_id == DATABASE_ID {
+      "listallinfo".to_string()
+    } else if let Some(name) = playlist_id.strip_prefix(STORED_PLAYLIST_PREFIX) {
+      format!("listplaylistinfo {}", quote(name))
+    } else {
+      return Err(QuizError::RuntimeError(format!("{} is no MPD playlist", playlist_id)));
+    };
+    let mut connection = self.connect().await?;
+    Ok(parse_songs(connection.command(&command).await?))
+  }
+
+  /// Replace the queue with the given song and play it from `start`
+  pub async fn play(&self, uri: &str, start: chrono::Duration) -> Result<(), QuizError> {
+    let mut connection = self.connect().await?;
+    connection.command("clear").await?;
+    let id = connection.command(&format!("addid {}", quote(uri))).await?
+      .into_iter()
+      .find(|(key, _)| key == "Id")
+      .map(|(_, id)| id)
+      .ok_or(QuizError::MpdError("No id for added song".to_string()))?;
+    // Seeking starts the playback at the position, playing first would let the beginning of the song be heard
+    connection.command(&format!("seekid {} {:.3}", id, start.num_milliseconds() as f64 / 1000.0)).await?;
+    Ok(())
+  }
+
+  /// Pause the playback
+  pub async fn pause(&self) -> Result<(), QuizError> {
+    self.connect().await?.command("pause 1").await.map(|_| ())
+  }
+
+  /// Stop the playback
+  pub async fn stop(&self) -> Result<(), QuizError> {
+    self.connect().await?.command("stop").await.map(|_| ())
+  }
+}
+
+impl MpdConnection {
+  /// Send a command and return the received key-value pairs. MPD ends every answer with `OK` or `ACK <error>`.
+  async fn command(&mut self, command: &str) -> Result<Vec<(String, String)>, QuizError> {
+    with_timeout(self.writer.write_all(format!("{}\n", command).as_bytes())).await?;
+    let mut pairs = vec![];
+    loop {
+      let mut line = String::new();
+      if with_timeout(self.reader.read_line(&mut line)).await? == 0 {
+        return Err(QuizError::MpdError("Connection closed".to_string()));
+      }
+      let line = line.trim_end_matches('\n');
+      if line == "OK" {
+        return Ok(pairs);
+      } else if let Some(error) = line.strip_prefix("ACK ") {
+        return Err(QuizError::MpdError(error.to_string()));
+      } else if let Some((key, value)) = line.split_once(": ") {
+        pairs.push((key.to_string(), value.to_string()));
+      }
+    }
+  }
+}
+
+/// Fail with an error if MPD does not respond in time, so a hanging server does not block the game
+async fn with_timeout<T>(action: impl Future<Output = std::io::Result<T>>) -> Result<T, QuizError> {
+  tokio::time::timeout(TIMEOUT, action).await
+    .map_err(|_| QuizError::MpdError("No response in time".to_string()))?
+    .map_err(QuizError::from)
+}
+
+/// Quote an argument of a command
+fn quote(arg: &str) -> String {
+  format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Build tracks from a song list, every song begins with a `file` key
+fn parse_songs(pairs: Vec<(String, String)>) -> Vec<Track> {
+  let mut songs: Vec<Vec<(String, String)>> = vec![];
+  for (key, value) in pairs {
+    match key.as_str() {
+      "file" => songs.push(vec![(key, value)]),
+      // directories and playlists are listed in between by listallinfo
+      "directory" | "playlist" => songs.push(vec![]),
+      _ => if let Some(song) = songs.last_mut() {
+        song.push((key, value));
+      }
+    }
+  }
+
+  songs.into_iter()
+    .filter_map(|song| {
+      let get = |name: &str| song.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+      let duration = get("duration").and_then(|d| d.parse::<f64>().ok())
+        .or(get("Time").and_then(|t| t.parse::<f64>().ok()))
+        .unwrap_or_default();
+      // Songs with several artists have an Artist entry for each of them
+      let artists: Vec<String> = song.iter().filter(|(key, _)| key == "Artist").map(|(_, value)| value.clone()).collect();
+      if artists.is_empty() {
+        return None;
+      }
+      Some(Track {
+        id: get("file")?,
+        title: get("Title")?,
+        artists,
+        album: get("Album"),
+        year: get("Date").and_then(|d| d.get(0..4).and_then(|y| y.parse().ok())),
+        duration: chrono::Duration::milliseconds((duration * 1000.0) as i64),
+        preview_url: None,
+        popularity: None,
+        cover_url: None,
+      })
+    })
+    .collect()
+}
+
+/// Quiz using the database of MPD, songs are played by MPD
+pub struct MpdQuiz {
+  client: MpdClient,
+  songs: Vec<SongQuestion>,
+  questions: Vec<Question>,
+}
+
+impl MpdQuiz {
+  pub fn new(client: MpdClient) -> MpdQuiz {
+    MpdQuiz {
+      client,
+      songs: vec![],
+      questions: vec![],
+    }
+  }
+}
+
+impl Quiz for MpdQuiz {
+  async fn generate_questions(&mut self, pref: &GamePreferences) -> Result<(), QuizError> {
+    let mut pools = vec![];
+    for p in pool_playlists(pref, QuizSource::Mpd)? {
+      let tracks = self.client.tracks(&p.playlist.id).await?;
+      log::info!("Found {} songs in MPD playlist {}", tracks.len(), p.playlist.id);
+      pools.push(TrackPool { tracks, weight: p.weight });
+    }
+
+    let (songs, questions) = create_questions(pools, pref)?;
+    self.songs = songs;
+    self.questions = questions;
+    Ok(())
+  }
+
+  /// Lets MPD play the song belonging to the question given by `index` from the start of its snippet
+  async fn begin_question_action(&mut self, index: usize, offset: Duration) -> Result<(), QuizError> {
+    let song = self.songs.get(index).ok_or(QuizError::RuntimeError("Invalid song index".to_string()))?;
+    self.client.play(&song.song.id, song.start + chrono::Duration::from_std(offset).unwrap_or_default()).await?;
+    log::info!("Begin question {} {} - {}", index, song.song.artist_credit(", "), song.song.title);
+    Ok(())
+  }
+
+  async fn stop_question_action(&mut self, index: usize) -> Result<(), QuizError> {
+    let song = &self.songs.get(index).ok_or(QuizError::RuntimeError("Invalid song index".to_string()))?.song;
+    self.client.pause().await?;
+    log::info!("End question {} {} - {}", index, song.artist_credit(", "), song.title);
+    Ok(())
+  }
+
+  fn get_questions(&self) -> &Vec<Question> {
+    &self.questions
+  }
+
+  async fn shutdown(&mut self) -> Result<(), QuizError> {
+    self.client.stop().await
+  }
+}
@@ -0,0 +1,164 @@
+use std::io::Cursor;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use rodio::Source;
+use tokio::sync::oneshot;
+use crate::quiz::{QuestionAudio, QuizError};
+
+/// Interval in which the volume is changed while fading
+const FADE_STEP: Duration = Duration::from_millis(20);
+
+enum PlayerCommand {
+  /// Play the audio file in `data` beginning at `start`, the result is sent back when the playback started
+  Play { data: bytes::Bytes, start: Duration, result: oneshot::Sender<Result<(), QuizError>> },
+  /// Stop playing immediately
+  Stop,
+  /// Reduce the volume to zero over the given time and stop afterwards
+  Fade(Duration),
+}
+
+struct Fade {
+  begin: Instant,
+  duration: Duration,
+}
+
+/// Handle to the audio player task which plays audio on the speakers of the host.
+///
+/// Rodio's output stream can not be sent between threads, so it is owned by an own thread that receives commands.
+/// See https://github.com/RustAudio/rodio/issues/171, a sink cannot be stopped and play sounds afterwards,
+/// so a new one is created for every playback.
+#[derive(Clone)]
+pub struct AudioPlayer {
+  tx: mpsc::Sender<PlayerCommand>,
+}
+
+impl AudioPlayer {
+  /// Spawn the player thread. The output device is opened on the first playback, so the server also runs on hosts
+  /// without speakers.
+  pub fn spawn() -> AudioPlayer {
+    let (tx, rx) = mpsc::channel::<PlayerCommand>();
+    std::thread::spawn(move || player_loop(rx));
+    AudioPlayer { tx }
+  }
+
+  /// Play an audio file (MP3, FLAC, OGG, WAV) given as bytes, beginning at `start`.
+  /// Anything that is still playing is stopped before.
+  pub async fn play(&self, data: bytes::Bytes, start: Duration) -> Result<(), QuizError> {
+    let (tx_result, rx_result) = oneshot::channel();
+    self.send(PlayerCommand::Play { data, start, result: tx_result })?;
+    rx_result.await.map_err(|_| QuizError::RuntimeError("Audio player stopped".to_string()))?
+  }
+
+  /// Stop the current playback
+  pub fn stop(&self) -> Result<(), QuizError> {
+    self.send(PlayerCommand::Stop)
+  }
+
+  /// Fade out the current playback in the given time
+  pub fn fade(&self, duration: Duration) -> Result<(), QuizError> {
+    self.send(PlayerCommand::Fade(duration))
+  }
+
+  fn send(&self, cmd: PlayerCommand) -> Result<(), QuizError> {
+    self.tx.send(cmd).map_err(|_| QuizError::RuntimeError("Audio player stopped".to_string()))
+  }
+}
+
+/// Cut the part of an audio file that is played for a question. The clip is a WAV file without the tags of the
+/// original, so clients can neither read the song from its metadata nor listen to more than the snippet.
+pub async fn clip(data: bytes::Bytes, start: Duration, length: Duration) -> Result<QuestionAudio, QuizError> {
+  // Decoding takes a moment for long songs
+  let wav = tokio::task::spawn_blocking(move || -> Result<Vec<u8>, QuizError> {
+    let source = rodio::Decoder::new(Cursor::new(data))?;
+    let (channels, sample_rate) = (source.channels(), source.sample_rate());
+    let samples: Vec<i16> = source.skip_duration(start).take_duration(length).collect();
+    Ok(wav_file(&samples, channels, sample_rate))
+  }).await.map_err(|e| QuizError::RuntimeError(e.to_string()))??;
+  Ok(QuestionAudio { data: wav.into(), content_type: "audio/wav".to_string() })
+}
+
+/// Encode samples as 16 bit PCM WAV file
+fn wav_file(samples: &[i16], channels: u16, sample_rate: u32) -> Vec<u8> {
+  let data_len = (samples.len() * 2) as u32;
+  let mut wav = Vec::with_capacity(44 + data_len as usize);
+  wav.extend_from_slice(b"RIFF");
+  wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+  wav.extend_from_slice(b"WAVEfmt ");
+  wav.extend_from_slice(&16u32.to_le_bytes());
+  wav.extend_from_slice(&1u16.to_le_bytes());
+  wav.extend_from_slice(&channels.to_le_bytes());
+  wav.extend_from_slice(&sample_rate.to_le_bytes());
+  wav.extend_from_slice(&(sample_rate * channels as u32 * 2).to_le_bytes());
+  wav.extend_from_slice(&(channels * 2).to_le_bytes());
+  wav.extend_from_slice(&16u16.to_le_bytes());
+  wav.extend_from_slice(b"data");
+  wav.extend_from_slice(&data_len.to_le_bytes());
+  for sample in samples {
+    wav.extend_from_slice(&sample.to_le_bytes());
+  }
+  wav
+}
+
+fn player_loop(rx: mpsc::Receiver<PlayerCommand>) {
+  let mut output: Option<(rodio::OutputStream, rodio::OutputStreamHandle)> = None;
+  let mut sink: Option<rodio::Sink> = None;
+  let mut fade: Option<Fade> = None;
+
+  loop {
+    // While fading, the volume has to be changed regularly
+    let cmd = if fade.is_some() {
+      match rx.recv_timeout(FADE_STEP) {
+        Ok(cmd) => Some(cmd),
+        Err(mpsc::RecvTimeoutError::Timeout) => None,
+        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+      }
+    } else {
+      match rx.recv() {
+        Ok(cmd) => Some(cmd),
+        Err(_) => return,
+      }
+    };
+
+    match cmd {
+      Some(PlayerCommand::Play { data, start, result }) => {
+        fade = None;
+        sink = None;
+        if output.is_none() {
+          match rodio::OutputStream::try_default() {
+            Ok(o) => output = Some(o),
+            Err(e) => {
+              let _ = result.send(Err(QuizError::RuntimeError(format!("No audio output: {}", e))));
+              continue;
+            }
+          }
+        }
+        let (_, handle) = output.as_ref().unwrap();
+        let play = || -> Result<rodio::Sink, QuizError> {
+          let new_sink = rodio::Sink::try_new(handle)?;
+          let source = rodio::Decoder::new(Cursor::new(data))?;
+          new_sink.append(source.skip_duration(start));
+          Ok(new_sink)
+        };
+        let _ = result.send(play().map(|s| sink = Some(s)));
+      }
+      Some(PlayerCommand::Stop) => {
+        fade = None;
+        sink = None;
+      }
+      Some(PlayerCommand::Fade(duration)) if sink.is_some() => {
+        fade = Some(Fade { begin: Instant::now(), duration });
+      }
+      Some(PlayerCommand::Fade(_)) | None => {}
+    }
+
+    if let Some(f) = &fade {
+      let progress = f.begin.elapsed().as_secs_f32() / f.duration.as_secs_f32().max(f32::EPSILON);
+      if progress >= 1.0 {
+        fade = None;
+        sink = None;
+      } else if let Some(s) = &sink {
+        s.set_volume(1.0 - progress);
+      }
+    }
+  }
+}
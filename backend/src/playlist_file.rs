@@ -0,0 +1,335 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::game::Playlist;
+use crate::local;
+use crate::quiz::{QuizError, Track};
+
+/// Prefix of playlist ids that belong to playlist files (M3U, PLS, XSPF)
+pub const PLAYLIST_FILE_PREFIX: &str = "file:";
+
+/// Supported playlist formats
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+  M3u,
+  Pls,
+  Xspf,
+}
+
+impl Format {
+  fn from_path(path: &Path) -> Option<Format> {
+    match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+      "m3u" | "m3u8" => Some(Format::M3u),
+      "pls" => Some(Format::Pls),
+      "xspf" => Some(Format::Xspf),
+      _ => None
+    }
+  }
+}
+
+/// Entry of a playlist file. Besides the location, playlists may contain some information about the song that is used
+/// if the song file itself has no tags.
+#[derive(Debug, Default)]
+struct Entry {
+  path: PathBuf,
+  title: Option<String>,
+  artist: Option<String>,
+  album: Option<String>,
+  duration: Option<chrono::Duration>,
+}
+
+/// Get all playlist files in `dir`
+pub fn find_playlists(dir: &Path) -> Vec<Playlist> {
+  let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+    Ok(entries) => entries
+      .filter_map(|entry| entry.ok())
+      .map(|entry| entry.path())
+      .filter(|path| path.is_file() && Format::from_path(path).is_some())
+      .collect(),
+    Err(e) => {
+      log::warn!("Could not read playlist directory {:?}: {}", dir, e);
+      return vec![];
+    }
+  };
+  files.sort();
+  files.iter()
+    .map(|path| Playlist {
+      name: playlist_name(path).unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().to_string()),
+      id: format!("{}{}", PLAYLIST_FILE_PREFIX, path.to_string_lossy()),
+    })
+    .collect()
+}
+
+/// Read the name stored in the playlist file, if there is one
+fn playlist_name(path: &Path) -> Option<String> {
+  let content = read_to_string(path).ok()?;
+  match Format::from_path(path)? {
+    Format::M3u => content.lines()
+      .find_map(|line| line.trim().strip_prefix("#PLAYLIST:"))
+      .map(|name| name.trim().to_string()),
+    Format::Pls => None,
+    Format::Xspf => {
+      let doc = roxmltree::Document::parse(&content).ok()?;
+      child_text(doc.root_element(), "title")
+    }
+  }
+}
+
+/// Read a playlist file and get all songs in it. Entries that can not be found, are not located in one of the `roots`
+/// or have neither tags nor information about title and artist in the playlist file are skipped.
+pub fn read_tracks(path: &Path, roots: &[PathBuf]) -> Result<Vec<Track>, QuizError> {
+  let format = Format::from_path(path)
+    .ok_or(QuizError::RuntimeError(format!("{:?} is no supported playlist file", path)))?;
+  let content = read_to_string(path)?;
+  let base_dir = path.parent().unwrap_or(Path::new(""));
+  let entries = match format {
+    Format::M3u => parse_m3u(&content, base_dir),
+    Format::Pls => parse_pls(&content, base_dir),
+    Format::Xspf => parse_xspf(&content, base_dir)?,
+  };
+
+  Ok(entries.into_iter()
+    .filter_map(|entry| {
+      if !entry.path.is_file() {
+        log::warn!("Playlist entry {:?} not found", entry.path);
+        return None;
+      }
+      let Some(path) = roots.iter().find_map(|root| local::ensure_inside(&entry.path, Some(root)).ok()) else {
+        log::warn!("Skipped playlist entry {:?}, it is not part of the configured directories", entry.path);
+        return None;
+      };
+      local::read_track(&path).or_else(|| Some(Track {
+        id: path.to_string_lossy().to_string(),
+        title: entry.title?,
+        artists: vec![entry.artist?],
+        album: entry.album,
+        year: None,
+        duration: entry.duration.unwrap_or_default(),
+        preview_url: None,
+        popularity: None,
+        cover_url: None,
+      }))
+    })
+    .collect())
+}
+
+/// Playlists are usually UTF-8, but old M3U files may also be Latin-1
+fn read_to_string(path: &Path) -> Result<String, QuizError> {
+  let bytes = fs::read(path)?;
+  Ok(match String::from_utf8(bytes) {
+    Ok(s) => s,
+    Err(e) => e.into_bytes().iter().map(|&b| b as char).collect()
+  })
+}
+
+/// Resolve a location in a playlist, which is either a path (relative to the playlist file or absolute) or a file URL
+fn resolve_location(location: &str, base_dir: &Path) -> Option<PathBuf> {
+  let location = location.trim();
+  if location.contains("://") {
+    match url::Url::parse(location) {
+      Ok(url) if url.scheme() == "file" => url.to_file_path().ok(),
+      _ => {
+        log::warn!("Skipped playlist entry {}, only local files are supported", location);
+        None
+      }
+    }
+  } else {
+    Some(base_dir.join(location))
+  }
+}
+
+/// Parse an (extended) M3U playlist, `#EXTINF:<seconds>,<artist> - <title>` lines belong to the following entry
+fn parse_m3u(content: &str, base_dir: &Path) -> Vec<Entry> {
+  let mut entries = vec![];
+  let mut info = Entry::default();
+  for line in content.lines().map(|l| l.trim_start_matches('\u{feff}').trim()) {
+    if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+      info = Entry::default();
+      if let Some((seconds, name)) = extinf.split_once(',') {
+        info.duration = seconds.trim().parse::<i64>().ok()
+          .filter(|s| *s > 0)
+          .map(chrono::Duration::seconds);
+        if let Some((artist, title)) = name.split_once(" - ") {
+          info.artist = Some(artist.trim().to_string());
+          info.title = Some(title.trim().to_string());
+        }
+      }
+    } else if !line.is_empty() && !line.starts_with('#') {
+      if let Some(path) = resolve_location(line, base_dir) {
+        entries.push(Entry { path, ..std::mem::take(&mut info) });
+      }
+      info = Entry::default();
+    }
+  }
+  entries
+}
+
+/// Parse a PLS playlist (ini format with `FileN`, `TitleN` and `LengthN` keys)
+fn parse_pls(content: &str, base_dir: &Path) -> Vec<Entry> {
+  let mut entries: Vec<(u32, Entry)> = vec![];
+  for line in content.lines() {
+    let Some((key, value)) = line.split_once('=') else { continue };
+    let key = key.trim().to_lowercase();
+    let (name, number) = key.split_at(key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len()));
+    let Ok(number) = number.parse::<u32>() else { continue };
+    let index = match entries.iter().position(|(n, _)| *n == number) {
+      Some(i) => i,
+      None => {
+        entries.push((number, Entry::default()));
+        entries.len() - 1
+      }
+    };
+    let entry = &mut entries[index].1;
+    match name {
+      "file" => entry.path = resolve_location(value, base_dir).unwrap_or_default(),
+      "title" => if let Some((artist, title)) = value.split_once(" - ") {
+        entry.artist = Some(artist.trim().to_string());
+        entry.title = Some(title.trim().to_string());
+      },
+      "length" => entry.duration = value.trim().parse::<i64>().ok()
+        .filter(|s| *s > 0)
+        .map(chrono::Duration::seconds),
+      _ => {}
+    }
+  }
+  entries.sort_by_key(|(n, _)| *n);
+  entries.into_iter()
+    .map(|(_, entry)| entry)
+    .filter(|entry| !entry.path.as_os_str().is_empty())
+    .collect()
+}
+
+/// Parse a XSPF playlist (XML, see https://xspf.org/spec)
+fn parse_xspf(content: &str, base_dir: &Path) -> Result<Vec<Entry>, QuizError> {
+  let doc = roxmltree::Document::parse(content)
+    .map_err(|e| QuizError::RuntimeError(format!("Invalid XSPF playlist: {}", e)))?;
+  Ok(doc.descendants()
+    .filter(|node| node.has_tag_name("track"))
+    .filter_map(|track| Some(Entry {
+      path: resolve_location(&child_text(track, "location")?, base_dir)?,
+      title: child_text(track, "title"),
+      artist: child_text(track, "creator"),
+      album: child_text(track, "album"),
+      duration: child_text(track, "duration")
+        .and_then(|d| d.parse::<i64>().ok())
+        .map(chrono::Duration::milliseconds),
+    }))
+    .collect())
+}
+
+fn child_text(node: roxmltree::Node, name: &str) -> Option<String> {
+  node.children()
+    .find(|child| child.has_tag_name(name))
+    .and_then(|child| child.text())
+    .map(|text| text.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Create an empty directory for the files of a test
+  fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("music-quiz-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn m3u_with_extinf() {
+    let content = "\u{feff}#EXTM3U\n#PLAYLIST:Party\n#EXTINF:215,Queen - Don't Stop Me Now\nqueen/dont_stop.mp3\n\n\
+                   # a comment\nother.flac\n#EXTINF:-1,Unknown\nfile:///music/x.ogg\nhttp://radio.example/stream\n";
+    let entries = parse_m3u(content, Path::new("/lists"));
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].path, PathBuf::from("/lists/queen/dont_stop.mp3"));
+    assert_eq!(entries[0].artist.as_deref(), Some("Queen"));
+    assert_eq!(entries[0].title.as_deref(), Some("Don't Stop Me Now"));
+    assert_eq!(entries[0].duration, Some(chrono::Duration::seconds(215)));
+    // information of an EXTINF line only belongs to the following entry
+    assert_eq!(entries[1].path, PathBuf::from("/lists/other.flac"));
+    assert!(entries[1].title.is_none());
+    assert_eq!(entries[2].path, PathBuf::from("/music/x.ogg"));
+    assert!(entries[2].duration.is_none() && entries[2].artist.is_none());
+  }
+
+  #[test]
+  fn pls_entries_in_order() {
+    let content = "[playlist]\nFile2=/music/b.mp3\nTitle2=ABBA - Waterloo\nLength2=168\n\
+                   File1=a.mp3\nTitle1=No separator\nNumberOfEntries=2\nVersion=2\n";
+    let entries = parse_pls(content, Path::new("/lists"));
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, PathBuf::from("/lists/a.mp3"));
+    assert!(entries[0].title.is_none());
+    assert_eq!(entries[1].path, PathBuf::from("/music/b.mp3"));
+    assert_eq!(entries[1].artist.as_deref(), Some("ABBA"));
+    assert_eq!(entries[1].title.as_deref(), Some("Waterloo"));
+    assert_eq!(entries[1].duration, Some(chrono::Duration::seconds(168)));
+  }
+
+  #[test]
+  fn xspf_tracks() {
+    let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+      <playlist version="1" xmlns="http://xspf.org/ns/0/">
+        <title>Oldies</title>
+        <trackList>
+          <track>
+            <location>file:///music/Beatles%20-%20Help.mp3</location>
+            <title>Help!</title>
+            <creator>The Beatles</creator>
+            <album>Help!</album>
+            <duration>138000</duration>
+          </track>
+          <track><location>relative/song.ogg</location></track>
+          <track><title>No location</title></track>
+        </trackList>
+      </playlist>"#;
+    let entries = parse_xspf(content, Path::new("/lists")).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, PathBuf::from("/music/Beatles - Help.mp3"));
+    assert_eq!(entries[0].title.as_deref(), Some("Help!"));
+    assert_eq!(entries[0].artist.as_deref(), Some("The Beatles"));
+    assert_eq!(entries[0].album.as_deref(), Some("Help!"));
+    assert_eq!(entries[0].duration, Some(chrono::Duration::milliseconds(138000)));
+    assert_eq!(entries[1].path, PathBuf::from("/lists/relative/song.ogg"));
+    assert!(parse_xspf("<playlist>", Path::new("/lists")).is_err());
+  }
+
+  #[test]
+  fn locations() {
+    let base = Path::new("/lists");
+    assert_eq!(resolve_location(" song.mp3 ", base), Some(PathBuf::from("/lists/song.mp3")));
+    assert_eq!(resolve_location("/music/song.mp3", base), Some(PathBuf::from("/music/song.mp3")));
+    assert_eq!(resolve_location("file:///music/a%20b.mp3", base), Some(PathBuf::from("/music/a b.mp3")));
+    assert_eq!(resolve_location("https://example.com/song.mp3", base), None);
+  }
+
+  #[test]
+  fn latin1_fallback() {
+    let dir = test_dir("latin1");
+    let file = dir.join("list.m3u");
+    fs::write(&file, b"#EXTINF:100,Die \xc4rzte - M\xe4dchen\n").unwrap();
+    assert_eq!(read_to_string(&file).unwrap(), "#EXTINF:100,Die Ärzte - Mädchen\n");
+    fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn entries_outside_of_roots_are_skipped() {
+    let dir = test_dir("roots");
+    let root = dir.join("music");
+    let outside = dir.join("private");
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    // Files without tags, so title and artist are taken from the playlist
+    fs::write(root.join("inside.mp3"), b"no audio").unwrap();
+    fs::write(outside.join("secret.mp3"), b"no audio").unwrap();
+    let list = root.join("list.m3u");
+    fs::write(&list, "#EXTINF:1,A - Inside\ninside.mp3\n#EXTINF:1,B - Secret\n../private/secret.mp3\n").unwrap();
+
+    let tracks = read_tracks(&list, std::slice::from_ref(&root)).unwrap();
+    assert_eq!(tracks.len(), 1);
+    assert_eq!(tracks[0].title, "Inside");
+    assert_eq!(tracks[0].artists, vec!["A".to_string()]);
+    assert_eq!(read_tracks(&list, &[root, outside]).unwrap().len(), 2);
+    fs::remove_dir_all(dir).unwrap();
+  }
+}
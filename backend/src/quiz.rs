@@ -1,251 +1,832 @@
-use std::cmp::max;
+use std::cmp::{max, min};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use crate::game::{Question, AnswerExposed};
-use rand::distributions::{Standard, Distribution};
-use rand::{random, Rng, thread_rng};
+use std::time::Duration;
+use crate::bingo::{BINGO_SIZE, SONGS_PER_DECOY};
+use crate::game::{clip_length, Question, AnswerExposed, AnswerKind, BingoContent, DistractorSource, DistractorStrategy, GamePreferences, PoolPlaylist,
+                  QuestionMedia, QuestionPart, SnippetStart, PART_SEPARATOR};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{Rng, thread_rng};
+use chrono::Datelike;
 use rand::prelude::IteratorRandom;
 use rand::seq::SliceRandom;
 use rspotify::{AuthCodeSpotify};
 use rspotify::clients::{BaseClient, OAuthClient};
-use rspotify::model::{Device, FullTrack, IdError, PlayableItem, PlaylistId};
-use rspotify::prelude::PlayableId;
+use rspotify::model::{AlbumId, AlbumType, ArtistId, Device, FullTrack, IdError, Market, PlayableItem, PlaylistId, PlaylistItem, TrackId};
+use rspotify::prelude::{Id, PlayableId};
+use rspotify::http::Query;
+use serde::{Deserialize, Serialize};
+use crate::player::{clip, AudioPlayer};
 use crate::spotify::CustomSpotifyChecks;
-use futures::StreamExt;
+use crate::titles::TitleCleaner;
+use crate::track_cache::TrackCache;
+use futures::{StreamExt, TryStreamExt};
 
 // Modi: Keine Anzeige der ausgewählten Antworten
-const ANSWER_COUNT: u32 = 4;
+/// Limits for the number of answers to choose from (`GamePreferences::answer_count`)
+pub const MIN_ANSWER_COUNT: u32 = 2;
+pub const MAX_ANSWER_COUNT: u32 = 8;
+/// Id of the pool containing the saved tracks of the user ("Liked Songs")
+pub const LIKED_SONGS_ID: &str = "spotify:collection";
+/// Suffix of an artist URI for the pool containing all songs of the artist (instead of the top tracks)
+pub const DISCOGRAPHY_SUFFIX: &str = ":discography";
+/// Number of songs with the most similar popularity the wrong answers are chosen from
+const SIMILAR_POPULARITY_TRACKS: usize = 10;
+/// Number of songs played for an order question
+const ORDER_CLIPS: usize = 3;
+/// Years added before the oldest and after the newest song for the range of year questions
+const YEAR_MARGIN: i32 = 5;
+/// Time to fade out songs played on the host when a question ends
+pub const FADE_OUT_TIME: Duration = Duration::from_millis(800);
+/// Length of the preview MP3s of Spotify
+const PREVIEW_LENGTH: Duration = Duration::from_secs(30);
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 enum AskedElement {
   Title,
   Artist,
+  Album,
+  Year,
+  Order,
+  Timeline,
+  Bingo,
+  /// Album of the cover shown instead of playing the song
+  CoverAlbum,
+  /// Artist of the cover shown instead of playing the song
+  CoverArtist,
+  /// Artist and title at once
+  ArtistTitle,
 }
 
-impl Distribution<AskedElement> for Standard {
-  fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> AskedElement {
-    match rng.gen_range(0..=1) {
-      0 => AskedElement::Title,
-      _ => AskedElement::Artist
+/// Song information independent from the source it was taken from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Track {
+  /// Source specific identifier (Spotify URI, file path, ...)
+  pub id: String,
+  pub title: String,
+  pub artists: Vec<String>,
+  pub album: Option<String>,
+  pub year: Option<i32>,
+  #[serde(with = "duration_ms")]
+  pub duration: chrono::Duration,
+  pub preview_url: Option<String>,
+  /// Popularity from 0 to 100 (Spotify only)
+  #[serde(default)]
+  pub popularity: Option<u32>,
+  /// Picture of the album cover
+  #[serde(default)]
+  pub cover_url: Option<String>,
+}
+
+/// Durations are stored as milliseconds
+mod duration_ms {
+  use serde::{Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(duration: &chrono::Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i64(duration.num_milliseconds())
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<chrono::Duration, D::Error> {
+    i64::deserialize(deserializer).map(chrono::Duration::milliseconds)
+  }
+}
+
+impl Track {
+  /// Names of all artists of the song joined by `separator`
+  pub fn artist_credit(&self, separator: &str) -> String {
+    self.artists.join(separator)
+  }
+}
+
+impl From<FullTrack> for Track {
+  fn from(track: FullTrack) -> Self {
+    Track {
+      id: track.id.map(|id| id.uri()).unwrap_or_default(),
+      title: track.name,
+      artists: track.artists.into_iter().map(|a| a.name).collect(),
+      // release date is given as YYYY-MM-DD, YYYY-MM or YYYY
+      year: track.album.release_date.as_ref().and_then(|d| d.get(0..4)).and_then(|y| y.parse().ok()),
+      album: Some(track.album.name),
+      duration: track.duration,
+      preview_url: track.preview_url,
+      popularity: Some(track.popularity),
+      // Take the biggest picture, it is shown on the host's display
+      cover_url: track.album.images.into_iter().max_by_key(|i| i.width).map(|i| i.url),
     }
   }
 }
 
 pub struct SongQuestion {
-  song: FullTrack,
-  preview_mp3: Option<bytes::Bytes>,
+  pub song: Track,
+  /// Position in the song where the playback starts
+  pub start: chrono::Duration,
   _asked: AskedElement, // todo: use or delete
 }
 
-pub struct SongQuiz {
-  // additional information about the questions/songs
-  songs: Vec<SongQuestion>,
-  // questions exposed for Quiz trait
-  questions: Vec<Question>,
-
-  spotify: Arc<AuthCodeSpotify>,
-
-  // _stream: rodio::OutputStream,
-  // stream_handle: rodio::OutputStreamHandle,
-  // sink: Option<rodio::Sink>,
-  preview_mode: bool,
+/// Songs of one playlist of the pool
+pub struct TrackPool {
+  pub tracks: Vec<Track>,
+  pub weight: u32,
 }
 
-impl SongQuiz {
-  pub fn new(auth: Arc<AuthCodeSpotify>, preview_mode: bool) -> SongQuiz {
-    // let (stream, stream_handle) = rodio::OutputStream::try_default().unwrap();
-    SongQuiz {
-      songs: vec![],
-      questions: vec![],
-      spotify: auth,
-      // _stream: stream,
-      // stream_handle,
-      // sink: None,
-      preview_mode,
-    }
+/// Get the playlists of the pool that belong to `source`. A round is played with one source only, so playlists of
+/// other sources are skipped.
+pub fn pool_playlists(pref: &GamePreferences, source: QuizSource) -> Result<Vec<PoolPlaylist>, QuizError> {
+  let (playlists, skipped): (Vec<PoolPlaylist>, Vec<PoolPlaylist>) = pref.pool()
+    .into_iter()
+    .partition(|p| QuizSource::from_playlist_id(&p.playlist.id) == Some(source));
+  for p in skipped {
+    log::warn!("Skipped playlist {} of another source", p.playlist.name);
+  }
+  if playlists.is_empty() {
+    return Err(QuizError::RuntimeError("No playlist selected".to_string()));
   }
+  Ok(playlists)
 }
 
-// impl<'a> Quiz for SongQuiz<'a> {
-impl SongQuiz {
-
-  /// Generates questions from the selected playlist saved internally
-  pub async fn generate_questions(&mut self, count: u32, playlist_id: &String, ask_artists: bool, ask_title: bool) -> Result<(), QuizError> {
-    let mut songs: Vec<SongQuestion> = vec![];
-    let mut questions: Vec<Question> = vec![];
+/// Chooses `pref.rounds` songs from the playlists in `pools` and creates a question for each of them.
+/// The questions contain the indices of the returned songs played for them (`clips`).
+pub fn create_questions(pools: Vec<TrackPool>, pref: &GamePreferences) -> Result<(Vec<SongQuestion>, Vec<Question>), QuizError> {
+  let mut songs: Vec<SongQuestion> = vec![];
+  let mut questions: Vec<Question> = vec![];
+  let count = pref.rounds;
+  let mut rng = thread_rng();
 
-    if !self.spotify.has_token().await {
-      return Err(QuizError::SpotifyAPIError("No spotify token"));
+  // Titles are cleaned of additions like "- Remastered 2011", so they neither give away the answer nor make
+  // several versions of a song look like different songs
+  let cleaner = TitleCleaner::new(pref);
+  let snippet_overrides = load_snippet_overrides();
+  // Songs contained in several playlists are only kept in the first one, playlists with a weight of zero come last so
+  // their songs can still be asked when they are in another playlist
+  let mut known = HashSet::new();
+  let mut tracks: Vec<Track> = vec![];
+  // index of the playlist in `pools` for every track
+  let mut origins: Vec<usize> = vec![];
+  let order = (0..pools.len()).filter(|&i| pools[i].weight > 0).chain((0..pools.len()).filter(|&i| pools[i].weight == 0));
+  for i in order {
+    for track in &pools[i].tracks {
+      // Timelines are built from release years
+      if pref.timeline_mode && track.year.is_none() {
+        continue;
+      }
+      // Answers about the artist are not possible without one
+      if track.artists.is_empty() {
+        log::debug!("Skipped {} without artist", track.title);
+        continue;
+      }
+      let title = cleaner.clean(&track.title);
+      if known.insert((title.to_lowercase(), track.artists.join(", ").to_lowercase())) {
+        tracks.push(Track { title, ..track.clone() });
+        origins.push(i);
+      }
     }
+  }
+
+  // Bingo cards are made of the songs of the round, there have to be enough for different cards
+  let bingo_songs = (BINGO_SIZE * BINGO_SIZE * 3 / 2) as u32;
+  if pref.bingo_mode && count < bingo_songs {
+    return Err(QuizError::RuntimeError(format!("Bingo needs at least {} songs per round", bingo_songs)));
+  }
 
-    let p_id = PlaylistId::from_uri(playlist_id.as_str())?;
-    let tracks = self.get_tracks(p_id).await;
+  // The last question has the most answers
+  let most_answers = question_answer_count(pref, count.saturating_sub(1));
+  if tracks.len() as u32 <= max(count, most_answers) {
+    return Err(QuizError::RuntimeError(format!(
+      "Playlists have {} tracks, but at least {} are needed",
+      tracks.len(),
+      max(count, most_answers))));
+  }
 
-    // Vectors needed for deduplication to not have the same answer twice
-    let songnames = Self::get_songnames(&tracks);
-    let artists = Self::get_artists(&tracks);
+  // Vectors needed for deduplication to not have the same answer twice, for the whole pool and every playlist
+  let pool_tracks = |pool: usize| tracks.iter().zip(&origins).filter(move |(_, o)| **o == pool).map(|(t, _)| t);
+  let songnames = get_songnames(tracks.iter());
+  let artists = get_artists(tracks.iter(), &pref.artist_separator);
+  let pool_songnames: Vec<Vec<String>> = (0..pools.len()).map(|i| get_songnames(pool_tracks(i))).collect();
+  let pool_artists: Vec<Vec<(String, Vec<String>)>> = (0..pools.len())
+    .map(|i| get_artists(pool_tracks(i), &pref.artist_separator))
+    .collect();
+  let albums = get_albums(tracks.iter());
+  let pool_albums: Vec<Vec<(String, String)>> = (0..pools.len()).map(|i| get_albums(pool_tracks(i))).collect();
 
-    if tracks.len() as u32 <= max(count, ANSWER_COUNT) {
-      return Err(QuizError::RuntimeError(format!(
-        "Playlist has {} tracks, but at least {} are needed",
-        tracks.len(),
-        max(count, ANSWER_COUNT))));
+  // Range of years offered for year questions
+  let year_range = year_range(&tracks);
+
+  // Choose songs to guess first to not have them twice, every song picks a playlist according to the weights
+  let mut remaining: Vec<Vec<usize>> = vec![vec![]; pools.len()];
+  for (i, origin) in origins.iter().enumerate() {
+    remaining[*origin].push(i);
+  }
+  let mut correct_songs: Vec<usize> = vec![];
+  // Every song of a bingo round needs its own entry on the cards (artists may have several songs)
+  let mut bingo_entries: HashSet<String> = HashSet::new();
+  while correct_songs.len() < count as usize {
+    let weights = remaining.iter().zip(&pools).map(|(r, p)| if r.is_empty() { 0 } else { p.weight });
+    let Ok(distribution) = WeightedIndex::new(weights) else { break };
+    let candidates = &mut remaining[distribution.sample(&mut rng)];
+    let song = candidates.swap_remove(rng.gen_range(0..candidates.len()));
+    if !pref.bingo_mode || bingo_entries.insert(bingo_entry(&tracks[song], pref).to_lowercase()) {
+      correct_songs.push(song);
     }
+  }
+  if correct_songs.len() < count as usize {
+    return Err(QuizError::RuntimeError(format!(
+      "Playlists with a weight above 0 have {} {}, but {} are needed", correct_songs.len(),
+      if pref.bingo_mode { "different bingo entries" } else { "tracks" }, count)));
+  }
+
+  // Entries of songs that are not played are mixed into the bingo cards, so the cards do not give away the songs
+  let decoys: Vec<String> = if pref.bingo_mode {
+    let mut known_entries = bingo_entries.clone();
+    (0..tracks.len())
+      .filter(|i| !correct_songs.contains(i))
+      .map(|i| bingo_entry(&tracks[i], pref))
+      .filter(|entry| known_entries.insert(entry.to_lowercase()))
+      .collect::<Vec<String>>()
+      .into_iter()
+      .choose_multiple(&mut rng, count as usize / SONGS_PER_DECOY)
+  } else {
+    vec![]
+  };
 
-    // Choose songs to guess first to not have them twice
-    let correct_songs: Vec<FullTrack> = tracks
-      .choose_multiple(&mut thread_rng(), count as usize)
-      .cloned()
+  let mut used: HashSet<usize> = correct_songs.iter().copied().collect();
+  for (i, track_index) in correct_songs.into_iter().enumerate() {
+    let correct_song = tracks[track_index].clone();
+    let origin = origins[track_index];
+    // Order questions need other songs with a different release year
+    let mut clips = vec![track_index];
+    if pref.ask_for_order && correct_song.year.is_some() {
+      clips.extend(choose_order_songs(&tracks, &used, track_index));
+    }
+    let answer_count = question_answer_count(pref, i as u32);
+    // Wrong answers for album questions, other editions of the album (e.g. deluxe) are no valid wrong answers
+    let album = correct_song.album.clone().unwrap_or_default();
+    let mut album_candidates: Vec<&String> = distractor_pool(&albums, &pool_albums[origin], answer_count, pref)
+      .iter()
+      .filter(|(a, name)| *a != correct_song.artists[0] || album_base_title(name) != album_base_title(&album))
+      .map(|(_, name)| name)
+      .filter(|name| name.to_lowercase() != album.to_lowercase())
       .collect();
+    album_candidates.sort();
+    album_candidates.dedup();
+    let enough_albums = album_candidates.len() + 1 >= answer_count as usize;
+    // Wrong answers for artist questions, credits with one of the artists of the song would be (partly) correct
+    let credited: Vec<String> = correct_song.artists.iter().map(|a| a.to_lowercase()).collect();
+    let artist_candidates: Vec<&String> = distractor_pool(&artists, &pool_artists[origin], answer_count, pref)
+      .iter()
+      .filter(|(_, names)| !names.iter().any(|n| credited.contains(&n.to_lowercase())))
+      .map(|(credit, _)| credit)
+      .collect();
+    let enough_artists = artist_candidates.len() + 1 >= answer_count as usize;
+    let asked = choose_asked_element(&correct_song, pref, enough_albums, enough_artists, clips.len() > 1);
+    if asked == AskedElement::Order {
+      used.extend(&clips);
+      clips.shuffle(&mut rng);
+    } else {
+      clips.truncate(1);
+    }
+
+    // todo: do not take string as id
 
-    for i in 0..count {
-      // Choose song from playlist as correct answer
-      let mut asked: AskedElement = random();
-      if !ask_artists && ask_title {
-        asked = AskedElement::Title;
-      } else if ask_artists && !ask_title {
-        asked = AskedElement::Artist;
+    // Question with answers to choose from (or to type) about the title, artist or album
+    let mut choice = |asked: AskedElement| -> (String, AnswerKind, Vec<AnswerExposed>) {
+      let (correct_string, candidates): (String, Vec<&String>) = match asked {
+        AskedElement::Artist | AskedElement::CoverArtist =>
+          (correct_song.artist_credit(&pref.artist_separator), artist_candidates.clone()),
+        AskedElement::Album | AskedElement::CoverAlbum => (album.clone(), album_candidates.clone()),
+        _ => (correct_song.title.clone(), distractor_pool(&songnames, &pool_songnames[origin], answer_count, pref).iter().collect())
+      };
+      if pref.free_text {
+        (correct_string, AnswerKind::Text, vec![])
+      } else {
+        // Prefer wrong answers matching the distractor strategy, the rest is chosen from all candidates
+        let valid: HashSet<&String> = candidates.iter().copied().filter(|answer| answer.to_lowercase() != correct_string.to_lowercase()).collect();
+        let scope = tracks.iter().zip(&origins)
+          .filter(|(_, o)| pref.distractor_source == DistractorSource::Pool || **o == origin)
+          .map(|(t, _)| t);
+        let mut preferred: Vec<&String> = strategy_tracks(scope, &correct_song, asked, pref.distractor_strategy)
+          .into_iter()
+          .filter_map(|t| answer_text(t, asked, pref))
+          .filter_map(|answer| valid.get(&answer).copied())
+          .collect();
+        preferred.sort();
+        preferred.dedup();
+        let mut wrong = preferred.into_iter().choose_multiple(&mut rng, (answer_count - 1) as usize);
+        let missing = (answer_count - 1) as usize - wrong.len();
+        let others = candidates.into_iter().filter(|answer| valid.contains(answer) && !wrong.contains(answer));
+        wrong.extend(others.choose_multiple(&mut rng, missing));
+        let mut answers: Vec<AnswerExposed> = wrong
+          .into_iter()
+          .map(|answer| AnswerExposed { text: answer.clone(), id: answer.clone() })
+          .collect();
+        answers.push(AnswerExposed { text: correct_string.clone(), id: correct_string.clone() });
+        answers.shuffle(&mut rng);
+        (correct_string, AnswerKind::Choice, answers)
+      }
+    };
+    let mut parts = vec![];
+    let (correct_string, kind, answers) = match asked {
+      AskedElement::Year => {
+        let year = correct_song.year.unwrap_or_default();
+        (year.to_string(), AnswerKind::Number { min: year_range.0, max: year_range.1 }, vec![])
+      }
+      AskedElement::Timeline => (correct_song.year.unwrap_or_default().to_string(), AnswerKind::Timeline, vec![]),
+      AskedElement::Bingo => (bingo_entry(&correct_song, pref), AnswerKind::Bingo, vec![]),
+      AskedElement::Order => {
+        // Songs are offered in the order they are played, the answer lists their ids from oldest to newest
+        let answers: Vec<AnswerExposed> = clips.iter().enumerate()
+          .map(|(k, &clip)| AnswerExposed { text: song_name(&tracks[clip], pref), id: (k + 1).to_string() })
+          .collect();
+        let mut by_year: Vec<(i32, &String)> = clips.iter().zip(&answers)
+          .map(|(&clip, answer)| (tracks[clip].year.unwrap_or_default(), &answer.id))
+          .collect();
+        by_year.sort();
+        let correct_string = by_year.iter().map(|(_, id)| id.as_str()).collect::<Vec<&str>>().join(",");
+        (correct_string, AnswerKind::Order, answers)
+      }
+      AskedElement::ArtistTitle => {
+        let (artist, _, artist_answers) = choice(AskedElement::Artist);
+        let (title, _, title_answers) = choice(AskedElement::Title);
+        parts = vec![
+          // Any credited artist is accepted when typing
+          QuestionPart { text: "Künstler".to_string(), answers: artist_answers, alternatives: correct_song.artists.clone() },
+          QuestionPart { text: "Titel".to_string(), answers: title_answers, alternatives: vec![] },
+        ];
+        (format!("{}{}{}", artist, PART_SEPARATOR, title), AnswerKind::Parts, vec![])
       }
+      _ => choice(asked)
+    };
 
-      let correct_song = &correct_songs[i as usize];
+    questions.push(Question {
+      text: match asked {
+        AskedElement::Title => "Wie heißt der Titel?".to_string(),
+        AskedElement::Artist => "Wie heißt der Künstler?".to_string(),
+        AskedElement::Album => "Auf welchem Album ist der Song erschienen?".to_string(),
+        AskedElement::Year => "In welchem Jahr ist der Song erschienen?".to_string(),
+        AskedElement::Order => "Welcher Song ist zuerst erschienen? Sortiere vom ältesten zum neuesten.".to_string(),
+        AskedElement::Timeline => "Wo gehört der Song in deine Zeitleiste?".to_string(),
+        AskedElement::Bingo => "Ist der Song auf deiner Bingokarte?".to_string(),
+        AskedElement::CoverAlbum => "Zu welchem Album gehört das Cover?".to_string(),
+        AskedElement::CoverArtist => "Von welchem Künstler ist das Album?".to_string(),
+        AskedElement::ArtistTitle => "Wie heißen Künstler und Titel?".to_string(),
+      },
+      kind,
+      answers,
+      parts,
+      correct: Some(correct_string),
+      solution: Some(match asked {
+        AskedElement::Year | AskedElement::Timeline =>
+          format!("{} ({})", song_name(&correct_song, pref), correct_song.year.unwrap_or_default()),
+        AskedElement::Order => {
+          let mut clip_tracks: Vec<&Track> = clips.iter().map(|&clip| &tracks[clip]).collect();
+          clip_tracks.sort_by_key(|t| t.year);
+          clip_tracks.iter()
+            .map(|t| format!("{} ({})", song_name(t, pref), t.year.unwrap_or_default()))
+            .collect::<Vec<String>>()
+            .join(" / ")
+        }
+        AskedElement::CoverAlbum | AskedElement::CoverArtist => format!("{} - {}",
+          correct_song.artist_credit(&pref.artist_separator), correct_song.album.clone().unwrap_or_default()),
+        _ => song_name(&correct_song, pref)
+      }),
+      media: match asked {
+        AskedElement::CoverAlbum | AskedElement::CoverArtist => correct_song.cover_url.clone()
+          .map(|url| QuestionMedia { url, effect: pref.cover_effect }),
+        _ => None
+      },
+      index: i as i32,
+      total_questions: count,
+      clips: (songs.len()..songs.len() + clips.len()).collect(),
+      // Any credited artist is accepted when typing
+      alternatives: if matches!(asked, AskedElement::Artist | AskedElement::CoverArtist) { correct_song.artists.clone() } else { vec![] },
+      decoy: decoys.get(i).cloned(),
+    });
+    for clip in clips {
       songs.push(SongQuestion {
-        song: correct_song.clone(),
-        preview_mp3: match &correct_song.preview_url {
-          Some(url) => {
-            if self.preview_mode {
-              let resp = reqwest::blocking::get(url).expect("No preview gotten");
-              Some(resp.bytes().unwrap())
-            } else {
-              None
-            }
-          }
-          None => None
-        },
+        song: tracks[clip].clone(),
+        start: snippet_start(&tracks[clip], pref, &snippet_overrides),
         _asked: asked,
       });
+    }
+  }
+  Ok((songs, questions))
+}
 
-      // todo: do not take string as id
-
-      let mut answers: Vec<AnswerExposed> = match asked {
-        AskedElement::Title => {
-          songnames
-            .iter()
-            .filter(|name| *name != &correct_song.name)
-            .choose_multiple(&mut thread_rng(), (ANSWER_COUNT - 1) as usize)
-            .iter()
-            .map(|song| AnswerExposed { text: (*song).clone(), id: (*song).clone() })
-            .collect()
-        }
-        AskedElement::Artist => {
-          artists
-            .iter()
-            .filter(|artist| *artist != &correct_song.artists.first().unwrap().name)
-            .choose_multiple(&mut thread_rng(), (ANSWER_COUNT - 1) as usize)
-            .iter()
-            .map(|artist| AnswerExposed { text: (*artist).clone(), id: (*artist).clone() })
-            .collect()
-        }
-      };
-      let correct_string = match asked {
-        AskedElement::Title => correct_song.name.clone(),
-        AskedElement::Artist => correct_song.artists.first().unwrap().name.clone()
-      };
-      let correct_answer = AnswerExposed { text: correct_string.clone(), id: correct_string.clone() };
-      answers.push(correct_answer.clone());
-      answers.shuffle(&mut thread_rng());
-
-      questions.push(Question {
-        text: match asked {
-          AskedElement::Title => "Wie heißt der Titel?".to_string(),
-          AskedElement::Artist => "Wie heißt der Künstler?".to_string()
-        },
-        answers,
-        correct: Some(correct_answer.id),
-        solution: Some(format!("{} - {}", correct_song.artists.first().unwrap().name.clone(), correct_song.name.clone())),
-        index: i as i32,
-        total_questions: count,
-      })
+/// Get what the bingo squares show for a song
+fn bingo_entry(track: &Track, pref: &GamePreferences) -> String {
+  match pref.bingo_content {
+    BingoContent::Titles => track.title.clone(),
+    BingoContent::Artists => track.artist_credit(&pref.artist_separator)
+  }
+}
+
+/// Read the start positions (in seconds) of single songs by their id, they take precedence over `pref.snippet_start`
+fn load_snippet_overrides() -> HashMap<String, f64> {
+  let Ok(file) = std::fs::File::open(crate::SNIPPETS_FILE) else {
+    return HashMap::new();
+  };
+  serde_json::from_reader(std::io::BufReader::new(file))
+    .inspect_err(|e| log::warn!("Invalid snippet file {}: {}", crate::SNIPPETS_FILE, e))
+    .unwrap_or_default()
+}
+
+/// Choose where the playback of a song starts. The snippet is moved forward if the song would end before the time to
+/// answer is over.
+fn snippet_start(track: &Track, pref: &GamePreferences, overrides: &HashMap<String, f64>) -> chrono::Duration {
+  let seconds = |s: f64| chrono::Duration::milliseconds((s * 1000.0) as i64);
+  let latest = max(track.duration - chrono::Duration::seconds(pref.time_to_answer as i64), chrono::Duration::zero());
+  // Progressive reveals start with the intro
+  let strategy = if pref.progressive_reveal { SnippetStart::Intro } else { pref.snippet_start };
+  let start = match overrides.get(&track.id) {
+    Some(s) => seconds(s.max(0.0)),
+    None => match strategy {
+      SnippetStart::Third => track.duration / 3,
+      SnippetStart::Intro => chrono::Duration::zero(),
+      SnippetStart::Fixed => chrono::Duration::seconds(pref.snippet_offset as i64),
+      SnippetStart::Random => {
+        let earliest = min(chrono::Duration::seconds(pref.snippet_offset as i64), latest);
+        chrono::Duration::milliseconds(thread_rng().gen_range(earliest.num_milliseconds()..=latest.num_milliseconds()))
+      }
     }
-    self.songs = songs;
-    self.questions = questions;
+  };
+  // The length of some songs is not known
+  if track.duration.is_zero() {
+    start
+  } else {
+    min(start, latest)
+  }
+}
 
-    Ok(())
+/// Choose what to ask for a song from the enabled question types. Years and albums can only be asked if the song has
+/// one, albums and artists also need enough other albums or artists for wrong answers and order questions other songs
+/// to compare with. Without any possible question type, the title is asked.
+fn choose_asked_element(track: &Track, pref: &GamePreferences, enough_albums: bool, enough_artists: bool,
+                        order_possible: bool) -> AskedElement {
+  if pref.bingo_mode {
+    return AskedElement::Bingo;
+  }
+  if pref.timeline_mode {
+    return AskedElement::Timeline;
+  }
+  let mut enabled = vec![];
+  if pref.ask_for_title {
+    enabled.push(AskedElement::Title);
+  }
+  if pref.ask_for_artist && enough_artists {
+    enabled.push(AskedElement::Artist);
+  }
+  if pref.ask_for_artist_and_title && enough_artists {
+    enabled.push(AskedElement::ArtistTitle);
+  }
+  if pref.ask_for_album && track.album.is_some() && enough_albums {
+    enabled.push(AskedElement::Album);
+  }
+  if pref.ask_for_year && track.year.is_some() {
+    enabled.push(AskedElement::Year);
+  }
+  if pref.ask_for_order && order_possible {
+    enabled.push(AskedElement::Order);
+  }
+  // The album is asked for covers if possible
+  if pref.ask_for_cover && track.cover_url.is_some() {
+    if track.album.is_some() && enough_albums {
+      enabled.push(AskedElement::CoverAlbum);
+    } else if enough_artists {
+      enabled.push(AskedElement::CoverArtist);
+    }
   }
+  enabled.choose(&mut thread_rng()).copied().unwrap_or(AskedElement::Title)
+}
 
-  fn get_artists(tracks: &Vec<FullTrack>) -> Vec<String> {
-    let mut artists: Vec<String> = tracks
-      .iter()
-      .map(|track| track.artists.first().unwrap().name.clone())
-      .collect();
-    artists.sort();
-    artists.dedup();
-    artists
+/// Choose up to `ORDER_CLIPS - 1` songs that are not used in other questions to be ordered together with the song
+/// given by `first`. All songs have different release years.
+fn choose_order_songs(tracks: &[Track], used: &HashSet<usize>, first: usize) -> Vec<usize> {
+  let mut years: Vec<i32> = tracks[first].year.into_iter().collect();
+  let mut chosen = vec![];
+  while chosen.len() + 1 < ORDER_CLIPS {
+    let candidate = (0..tracks.len())
+      .filter(|i| !used.contains(i) && tracks[*i].year.is_some_and(|y| !years.contains(&y)))
+      .choose(&mut thread_rng());
+    match candidate {
+      Some(i) => {
+        years.extend(tracks[i].year);
+        chosen.push(i);
+      }
+      None => break
+    }
+  }
+  chosen
+}
+
+fn song_name(track: &Track, pref: &GamePreferences) -> String {
+  format!("{} - {}", track.artist_credit(&pref.artist_separator), track.title)
+}
+
+/// Get the range of years players can choose from: The years of the songs with some margin, but not in the future
+fn year_range(tracks: &[Track]) -> (i32, i32) {
+  let current_year = chrono::Utc::now().year();
+  let years = tracks.iter().filter_map(|t| t.year);
+  match (years.clone().min(), years.max()) {
+    (Some(first), Some(last)) => (first - YEAR_MARGIN, min(last + YEAR_MARGIN, current_year)),
+    _ => (current_year - 100, current_year)
   }
+}
 
-  fn get_songnames(tracks: &Vec<FullTrack>) -> Vec<String> {
-    let mut songnames: Vec<String> = tracks
-      .iter()
-      .map(|track| track.name.clone())
-      .collect();
-    songnames.sort();
-    songnames.dedup();
-    songnames
+/// Get the tracks whose answers are preferred as wrong answers by the distractor strategy, empty if there are none
+fn strategy_tracks<'a>(candidates: impl Iterator<Item = &'a Track>, correct: &Track, asked: AskedElement,
+                       strategy: DistractorStrategy) -> Vec<&'a Track> {
+  match strategy {
+    DistractorStrategy::SameDecade => match correct.year {
+      Some(year) => candidates.filter(|t| t.year.is_some_and(|y| y / 10 == year / 10)).collect(),
+      None => vec![]
+    },
+    DistractorStrategy::SameArtist if asked == AskedElement::Title => {
+      candidates.filter(|t| t.artists.first() == correct.artists.first()).collect()
+    }
+    DistractorStrategy::SimilarPopularity => match correct.popularity {
+      Some(popularity) => {
+        let mut similar: Vec<&Track> = candidates.filter(|t| t.popularity.is_some()).collect();
+        similar.sort_by_key(|t| t.popularity.unwrap_or_default().abs_diff(popularity));
+        similar.truncate(SIMILAR_POPULARITY_TRACKS);
+        similar
+      }
+      None => vec![]
+    },
+    DistractorStrategy::Uniform | DistractorStrategy::SameArtist => vec![]
+  }
+}
+
+/// Get the answer a track gives for the asked element
+fn answer_text(track: &Track, asked: AskedElement, pref: &GamePreferences) -> Option<String> {
+  match asked {
+    AskedElement::Title => Some(track.title.clone()),
+    AskedElement::Artist | AskedElement::CoverArtist => Some(track.artist_credit(&pref.artist_separator)),
+    AskedElement::Album | AskedElement::CoverAlbum => track.album.clone(),
+    _ => None
+  }
+}
+
+/// Get the number of answers to choose from for the question given by `index`. With the difficulty ramp, it rises
+/// from `pref.answer_count` for the first question to the maximum for the last one.
+fn question_answer_count(pref: &GamePreferences, index: u32) -> u32 {
+  let first = pref.answer_count.clamp(MIN_ANSWER_COUNT, MAX_ANSWER_COUNT);
+  if pref.answer_count_ramp && pref.rounds > 1 {
+    first + (MAX_ANSWER_COUNT - first) * index.min(pref.rounds - 1) / (pref.rounds - 1)
+  } else {
+    first
+  }
+}
+
+/// Take wrong answers from the song's playlist if wanted and it has enough different ones
+fn distractor_pool<'a, T>(all: &'a [T], own: &'a [T], answer_count: u32, pref: &GamePreferences) -> &'a [T] {
+  if pref.distractor_source == DistractorSource::OwnPlaylist && own.len() >= answer_count as usize {
+    own
+  } else {
+    all
+  }
+}
+
+/// Get the title of an album without edition suffixes like " (Deluxe Edition)" or " - Remastered", in lowercase
+fn album_base_title(name: &str) -> String {
+  let end = [" (", " [", " - "].iter()
+    .filter_map(|separator| name.find(separator))
+    .min()
+    .unwrap_or(name.len());
+  name[..end].trim().to_lowercase()
+}
+
+/// Get all albums with their artist, other editions of an album by the same artist are left out
+fn get_albums<'a>(tracks: impl Iterator<Item = &'a Track>) -> Vec<(String, String)> {
+  let mut albums: Vec<(String, String)> = tracks
+    .filter_map(|track| Some((track.artists.first()?.clone(), track.album.clone()?)))
+    .collect();
+  albums.sort();
+  albums.dedup_by(|a, b| a.0 == b.0 && album_base_title(&a.1) == album_base_title(&b.1));
+  albums
+}
+
+/// Get all artist credits with the individual artists. Credits of the same artists in another order are left out.
+fn get_artists<'a>(tracks: impl Iterator<Item = &'a Track>, separator: &str) -> Vec<(String, Vec<String>)> {
+  let mut artists: Vec<(Vec<String>, String, Vec<String>)> = tracks
+    .map(|track| {
+      let mut key: Vec<String> = track.artists.iter().map(|a| a.to_lowercase()).collect();
+      key.sort();
+      (key, track.artist_credit(separator), track.artists.clone())
+    })
+    .collect();
+  artists.sort();
+  artists.dedup_by(|a, b| a.0 == b.0);
+  artists.into_iter().map(|(_, credit, names)| (credit, names)).collect()
+}
+
+fn get_songnames<'a>(tracks: impl Iterator<Item = &'a Track>) -> Vec<String> {
+  let mut songnames: Vec<String> = tracks
+    .map(|track| track.title.clone())
+    .collect();
+  // Titles only differing in case are the same song
+  songnames.sort_by_key(|s| s.to_lowercase());
+  songnames.dedup_by(|a, b| a.to_lowercase() == b.to_lowercase());
+  songnames
+}
+
+pub struct SongQuiz {
+  // additional information about the questions/songs
+  songs: Vec<SongQuestion>,
+  // questions exposed for Quiz trait
+  questions: Vec<Question>,
+
+  spotify: Arc<AuthCodeSpotify>,
+  cache: TrackCache,
+
+  // plays the preview MP3s in preview mode
+  player: AudioPlayer,
+  preview_mode: bool,
+  // clip of the current question in preview mode
+  clip: Option<(usize, QuestionAudio)>,
+  clip_length: Duration,
+}
+
+impl SongQuiz {
+  pub fn new(auth: Arc<AuthCodeSpotify>, cache: TrackCache, player: AudioPlayer, preview_mode: bool) -> SongQuiz {
+    SongQuiz {
+      songs: vec![],
+      questions: vec![],
+      spotify: auth,
+      cache,
+      player,
+      preview_mode,
+      clip: None,
+      clip_length: Duration::ZERO,
+    }
   }
 
-  async fn get_tracks(&mut self, p_id: PlaylistId<'_>) -> Vec<FullTrack> {
+  /// Get the tracks of a track pool given by its id, which is a playlist, album or artist URI or one of
+  /// `LIKED_SONGS_ID` and `spotify:artist:<id>:discography`
+  async fn get_tracks(&self, id: &str) -> Result<Vec<Track>, QuizError> {
     let preview_mode = self.preview_mode;
 
-    // Limiting fields like Some("limit,next,offset,total,href,items(is_local,track)") is not possible without
-    // including all fields in PlayableItem needed to deserialize it (so I don't)
-    let tracks: Vec<rspotify::model::FullTrack> = self.spotify
-      .playlist_items(p_id, None, None)
-      .filter_map(|res| async move {
-        res.inspect_err(|e| log::warn!("Error getting playlist items: {}", e)).ok()
-      } )
-      .filter_map(|item| async move {
-        if item.track.is_none() {
-          log::warn!("Filtered out item with no track: {:?}", item);
+    let tracks: Vec<Track> = if id == LIKED_SONGS_ID {
+      self.spotify
+        .current_user_saved_tracks(None)
+        .filter_map(|res| async move {
+          res.inspect_err(|e| log::warn!("Error getting saved tracks: {}", e)).ok()
+        })
+        .map(|saved| Track::from(saved.track))
+        .collect()
+        .await
+    } else if let Some(artist_uri) = id.strip_suffix(DISCOGRAPHY_SUFFIX) {
+      self.get_discography(ArtistId::from_uri(artist_uri)?).await?.into_iter().map(Track::from).collect()
+    } else if id.starts_with("spotify:artist:") {
+      self.spotify.artist_top_tracks(ArtistId::from_uri(id)?, Some(Market::FromToken)).await?
+        .into_iter().map(Track::from).collect()
+    } else if id.starts_with("spotify:album:") {
+      let track_ids = self.get_album_track_ids(AlbumId::from_uri(id)?).await;
+      self.get_full_tracks(track_ids).await?.into_iter().map(Track::from).collect()
+    } else {
+      let playlist_id = PlaylistId::from_uri(id)?;
+      let snapshot_id = snapshot_id(&self.spotify, &playlist_id).await?;
+      match self.cache.get(id, &snapshot_id) {
+        Some(tracks) => {
+          log::info!("Using {} cached tracks of {}", tracks.len(), id);
+          tracks
         }
-        item.track })
-      .filter_map(|item| async move {
-        match item {
-          PlayableItem::Track(t) => Some(t),
-          PlayableItem::Episode(e) => {
-            log::warn!("Filtered out an episode: {:?}", e.name);
-            None
-          }
+        None => {
+          let tracks = playlist_tracks(&self.spotify, playlist_id).await?;
+          self.cache.store(id, &snapshot_id, &tracks);
+          tracks
         }
-      })
-      .filter(|item| {
-        let preview_url = item.preview_url.clone(); // Clone the field to pass to the async block
-        if preview_mode && preview_url.is_none() {
-          log::info!("Filtered out due to missing preview_url: {:?} - {}", item.name, item.artists.first().unwrap().name);
+      }
+    };
+
+    Ok(tracks.into_iter()
+      .filter(|track| {
+        if preview_mode && track.preview_url.is_none() {
+          log::info!("Filtered out due to missing preview_url: {:?} - {}", track.title, track.artist_credit(", "));
         }
-        async move { !preview_mode || preview_url.is_some() } // Use the cloned value
+        !preview_mode || track.preview_url.is_some()
+      })
+      .collect())
+  }
+
+  async fn get_album_track_ids(&self, album_id: AlbumId<'_>) -> Vec<TrackId<'static>> {
+    self.spotify
+      .album_track(album_id, None)
+      .filter_map(|res| async move {
+        res.inspect_err(|e| log::warn!("Error getting album tracks: {}", e)).ok()
+      })
+      .filter_map(|track| async move { track.id })
+      .collect()
+      .await
+  }
+
+  /// Album tracks do not contain all information, so the full tracks are requested
+  async fn get_full_tracks(&self, track_ids: Vec<TrackId<'static>>) -> Result<Vec<FullTrack>, QuizError> {
+    let mut tracks = vec![];
+    // Spotify allows at most 50 tracks per request
+    for chunk in track_ids.chunks(50) {
+      tracks.extend(self.spotify.tracks(chunk.iter().cloned(), None).await?);
+    }
+    Ok(tracks)
+  }
+
+  /// Get all tracks from the albums and singles of an artist. Songs that appear on several albums are only taken once.
+  async fn get_discography(&self, artist_id: ArtistId<'_>) -> Result<Vec<FullTrack>, QuizError> {
+    let albums: Vec<AlbumId> = self.spotify
+      .artist_albums(artist_id, [AlbumType::Album, AlbumType::Single], None)
+      .filter_map(|res| async move {
+        res.inspect_err(|e| log::warn!("Error getting artist albums: {}", e)).ok()
       })
+      .filter_map(|album| async move { album.id })
       .collect()
       .await;
-    tracks
+    let mut track_ids = vec![];
+    for album in albums {
+      track_ids.extend(self.get_album_track_ids(album).await);
+    }
+    let mut tracks = self.get_full_tracks(track_ids).await?;
+    let mut names = std::collections::HashSet::new();
+    tracks.retain(|track| names.insert(track.name.to_lowercase()));
+    Ok(tracks)
+  }
+}
+
+/// Get all tracks of a Spotify playlist, episodes and unavailable items are skipped.
+/// Fails if a page of the playlist could not be loaded, so incomplete playlists are not cached.
+pub async fn playlist_tracks(spotify: &AuthCodeSpotify, p_id: PlaylistId<'_>) -> Result<Vec<Track>, QuizError> {
+  // Limiting fields like Some("limit,next,offset,total,href,items(is_local,track)") is not possible without
+  // including all fields in PlayableItem needed to deserialize it (so I don't)
+  let items: Vec<PlaylistItem> = spotify
+    .playlist_items(p_id, None, None)
+    .try_collect()
+    .await?;
+  Ok(items.into_iter()
+    .filter_map(|item| {
+      if item.track.is_none() {
+        log::warn!("Filtered out item with no track: {:?}", item);
+      }
+      item.track })
+    .filter_map(|item| {
+      match item {
+        PlayableItem::Track(t) => Some(Track::from(t)),
+        PlayableItem::Episode(e) => {
+          log::warn!("Filtered out an episode: {:?}", e.name);
+          None
+        }
+      }
+    })
+    .collect())
+}
+
+/// Get the current snapshot id of a playlist, which changes with every modification of the playlist
+pub async fn snapshot_id(spotify: &AuthCodeSpotify, p_id: &PlaylistId<'_>) -> Result<String, QuizError> {
+  #[derive(Deserialize)]
+  struct Snapshot {
+    snapshot_id: String,
+  }
+  // Requesting the whole playlist would include the first page of tracks, so only the needed field is requested
+  let response = spotify
+    .api_get(&format!("playlists/{}", p_id.id()), &Query::from([("fields", "snapshot_id")]))
+    .await?;
+  let snapshot: Snapshot = serde_json::from_str(&response)
+    .map_err(|_| QuizError::SpotifyAPIError("Invalid snapshot id response"))?;
+  Ok(snapshot.snapshot_id)
+}
+
+impl Quiz for SongQuiz {
+
+  /// Generates questions from the selected playlists saved internally
+  async fn generate_questions(&mut self, pref: &GamePreferences) -> Result<(), QuizError> {
+    if !self.spotify.has_token().await {
+      return Err(QuizError::SpotifyAPIError("No spotify token"));
+    }
+
+    let mut pools = vec![];
+    for p in pool_playlists(pref, QuizSource::Spotify)? {
+      pools.push(TrackPool { tracks: self.get_tracks(&p.playlist.id).await?, weight: p.weight });
+    }
+
+    let (songs, questions) = create_questions(pools, pref)?;
+    self.songs = songs;
+    self.clip_length = clip_length(pref);
+    self.questions = questions;
+
+    Ok(())
   }
 
   /// Plays the song belonging to the question given by `index`
-  pub async fn begin_question_action(&mut self, index: usize) -> Result<(), QuizError> {
-    if index > self.songs.len() {
+  async fn begin_question_action(&mut self, index: usize, offset: Duration) -> Result<(), QuizError> {
+    if index >= self.songs.len() {
       Err(QuizError::RuntimeError("Invalid song index".to_string()))
     } else {
       if self.preview_mode {
         // Use song preview MP3 in preview mode
-        let bytes = self.songs[index].preview_mp3.take().ok_or(QuizError::RuntimeError("No preview in preview mode".to_string()))?;
-        // see https://github.com/RustAudio/rodio/issues/171, sink cannot be stopped and play sounds afterwards
-        // so we have to create a new one every time
-        // self.sink = Some(rodio::Sink::try_new(&self.stream_handle)?);
-        // let cursor = std::io::Cursor::new(bytes);
-        // let source = rodio::Decoder::new(cursor)?;
-        // self.sink.as_ref().unwrap().append(source);
+        let audio = self.question_audio(index).await?
+          .ok_or(QuizError::RuntimeError("No preview in preview mode".to_string()))?;
+        self.player.play(audio.data, offset).await?;
       } else {
         // Use a spotify player running somewhere (we take the currently active device or the first one if there is no
         // active one
         let song = &self.songs[index].song;
-        let track_id = song.id.clone().ok_or(QuizError::RuntimeError("No TrackId available".to_string()))?;
+        let track_id = TrackId::from_uri(&song.id)?;
         let uris: Vec<PlayableId> = vec![PlayableId::Track(track_id)]; // Convert TrackId to PlayableId::Track
         let devices = self.spotify.device().await?;
         let mut playback_device: Option<&Device> = devices.iter().find(|dev| dev.is_active);
@@ -260,34 +841,63 @@ impl SongQuiz {
         self.spotify.start_uris_playback(uris,
                                          Some(device_id),
                                          None,
-                                         Some(song.duration / 3)).await?;
+                                         Some(self.songs[index].start + chrono::Duration::from_std(offset).unwrap_or_default())).await?;
       }
-      log::info!("Begin question {} {} - {}", index, self.songs[index].song.artists.first().unwrap().name, self.songs[index].song.name);
+      log::info!("Begin question {} {} - {}", index, self.songs[index].song.artist_credit(", "), self.songs[index].song.title);
       Ok(())
     }
   }
 
   /// Stops the playing songs
-  pub async fn stop_question_action(&mut self, index: usize) -> Result<(), QuizError> {
-    if index > self.songs.len() {
+  async fn stop_question_action(&mut self, index: usize) -> Result<(), QuizError> {
+    if index >= self.songs.len() {
       Err(QuizError::RuntimeError("Invalid song index".to_string()))
     } else {
-      // self.sink = None;
-      self.spotify.pause_playback(None).await?;
-      log::info!("End question {} {} - {}", index, self.songs[index].song.artists.first().unwrap().name, self.songs[index].song.name);
+      if self.preview_mode {
+        self.player.fade(FADE_OUT_TIME)?;
+      } else {
+        self.spotify.pause_playback(None).await?;
+      }
+      log::info!("End question {} {} - {}", index, self.songs[index].song.artist_credit(", "), self.songs[index].song.title);
       Ok(())
     }
   }
 
+  async fn cut_question_action(&mut self, index: usize) -> Result<(), QuizError> {
+    if self.preview_mode {
+      self.player.stop()
+    } else {
+      self.stop_question_action(index).await
+    }
+  }
+
   /// Get the questions generated before with `generate_questions(...)`
-  pub fn get_questions(&self) -> &Vec<Question> {
+  fn get_questions(&self) -> &Vec<Question> {
     &self.questions
   }
 
-  pub async fn shutdown(&mut self) -> Result<(), QuizError> {
-    // self.sink = None;
+  /// A clip of the preview MP3 in preview mode, songs played on a spotify device are not available
+  async fn question_audio(&mut self, index: usize) -> Result<Option<QuestionAudio>, QuizError> {
+    let song = match self.songs.get(index) {
+      Some(song) if self.preview_mode => song,
+      _ => return Ok(None)
+    };
+    let Some(url) = &song.song.preview_url else { return Ok(None) };
+    if let Some((_, audio)) = self.clip.as_ref().filter(|(i, _)| *i == index) {
+      return Ok(Some(audio.clone()));
+    }
+    let data = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+    // The snippet has to fit into the preview, which is only a part of the song
+    let latest = PREVIEW_LENGTH.saturating_sub(self.clip_length);
+    let audio = clip(data, song.start.to_std().unwrap_or_default().min(latest), self.clip_length).await?;
+    self.clip = Some((index, audio.clone()));
+    Ok(Some(audio))
+  }
+
+  async fn shutdown(&mut self) -> Result<(), QuizError> {
+    self.player.stop()?;
     if self.spotify.has_token().await {
-      let _ = self.spotify.pause_playback(None).await?;
+      self.spotify.pause_playback(None).await?;
       Ok(())
     } else {
       Err(QuizError::SpotifyAPIError("No spotify token"))
@@ -295,18 +905,84 @@ impl SongQuiz {
   }
 }
 
-// pub trait Quiz {
-//   fn generate_questions(&mut self, count: u32, playlist_id: PlaylistId);
-//   fn begin_question_action(&self, index: usize) -> Result<(), &'static str>;
-//   fn stop_question_action(&self, index: usize) -> Result<(), &'static str>;
-//   fn get_questions(&self) -> &Vec<Question>;
-// }
+/// Audio clip of a question to be played by the clients, see `player::clip`
+#[derive(Clone)]
+pub struct QuestionAudio {
+  pub data: bytes::Bytes,
+  pub content_type: String,
+}
+
+/// A source of songs for a game round. Every backend (Spotify, ...) implements this so `game_round` does not need to
+/// know where questions come from and how songs are played.
+pub trait Quiz {
+  /// Generates `pref.rounds` questions from the playlist selected in `pref`
+  async fn generate_questions(&mut self, pref: &GamePreferences) -> Result<(), QuizError>;
+
+  /// Starts the action belonging to the question given by `index` (usually playing the song). `offset` is the time the
+  /// song has already been played before it was paused, zero for a new question.
+  async fn begin_question_action(&mut self, index: usize, offset: Duration) -> Result<(), QuizError>;
+
+  /// Stops the action belonging to the question given by `index`
+  async fn stop_question_action(&mut self, index: usize) -> Result<(), QuizError>;
+
+  /// Stops the action belonging to the question given by `index` at once, songs are not faded out. Used between the
+  /// stages of a progressive reveal, which have to be exactly as long as announced.
+  async fn cut_question_action(&mut self, index: usize) -> Result<(), QuizError> {
+    self.stop_question_action(index).await
+  }
+
+  /// Get the questions generated before with `generate_questions(...)`
+  fn get_questions(&self) -> &Vec<Question>;
+
+  /// Get the audio of the question given by `index` so clients can play it on their own.
+  /// `None` if the song is only played by an external player.
+  async fn question_audio(&mut self, _index: usize) -> Result<Option<QuestionAudio>, QuizError> {
+    Ok(None)
+  }
+
+  /// Called once at the end of the round to stop everything that may still be running
+  async fn shutdown(&mut self) -> Result<(), QuizError>;
+}
+
+/// Backends that can be used as a song source. Which one is used for a round is determined by the prefix of the
+/// selected playlist's id.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum QuizSource {
+  Spotify,
+  Local,
+  Subsonic,
+  Mpd,
+}
 
+impl QuizSource {
+  /// Get the source a playlist belongs to, `None` if the id has no known prefix
+  pub fn from_playlist_id(id: &str) -> Option<QuizSource> {
+    if id.starts_with("spotify:") {
+      Some(QuizSource::Spotify)
+    } else if id.starts_with(crate::local::LOCAL_PREFIX) || id.starts_with(crate::playlist_file::PLAYLIST_FILE_PREFIX) {
+      Some(QuizSource::Local)
+    } else if id.starts_with(crate::subsonic::SUBSONIC_PREFIX) {
+      Some(QuizSource::Subsonic)
+    } else if id.starts_with(crate::mpd::MPD_PREFIX) {
+      Some(QuizSource::Mpd)
+    } else {
+      None
+    }
+  }
+}
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum QuizError {
   #[error("Error when using spotify API: {0}")]
   SpotifyAPIError(&'static str),
 
+  #[error("Error when using subsonic API: {0}")]
+  SubsonicAPIError(String),
+
+  #[error("Error when using MPD: {0}")]
+  MpdError(String),
+
   #[error("RSpotifyError: {0}")]
   RSpotifyIdError(#[from] IdError),
 
@@ -321,4 +997,56 @@ pub enum QuizError {
 
   #[error("RodioDecoderError: {0}")]
   RodioDecoderError(#[from] rodio::decoder::DecoderError),
+
+  #[error("ReqwestError: {0}")]
+  ReqwestError(#[from] reqwest::Error),
+
+  #[error("IOError: {0}")]
+  IOError(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn track(title: &str, artist: &str) -> Track {
+    Track {
+      id: format!("{} - {}", artist, title),
+      title: title.to_string(),
+      artists: vec![artist.to_string()],
+      album: None,
+      year: None,
+      duration: chrono::Duration::seconds(180),
+      preview_url: None,
+      popularity: None,
+      cover_url: None,
+    }
+  }
+
+  #[test]
+  fn title_is_asked_without_enough_artists() {
+    let tracks = (0..10).map(|i| track(&format!("Song {}", i), if i % 2 == 0 { "A" } else { "B" })).collect();
+    let mut pref = GamePreferences::new();
+    pref.ask_for_title = false;
+    pref.answer_count = 4;
+    let (_, questions) = create_questions(vec![TrackPool { tracks, weight: 1 }], &pref).unwrap();
+    assert_eq!(questions.len(), 5);
+    for question in questions {
+      assert_eq!(question.text, "Wie heißt der Titel?");
+      assert_eq!(question.answers.len(), 4);
+    }
+  }
+
+  #[test]
+  fn artists_are_asked_with_enough_artists() {
+    let tracks = (0..10).map(|i| track(&format!("Song {}", i), &format!("Artist {}", i))).collect();
+    let mut pref = GamePreferences::new();
+    pref.ask_for_title = false;
+    pref.answer_count = 4;
+    let (_, questions) = create_questions(vec![TrackPool { tracks, weight: 1 }], &pref).unwrap();
+    for question in questions {
+      assert_eq!(question.text, "Wie heißt der Künstler?");
+      assert_eq!(question.answers.len(), 4);
+    }
+  }
 }
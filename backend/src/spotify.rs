@@ -6,12 +6,16 @@ use rspotify::AuthCodeSpotify;
 use rspotify::clients::{BaseClient, OAuthClient};
 use crate::{GamePreferences, GameReferences};
 use chrono::prelude::*;
-use rspotify::model::Id;
+use rspotify::model::{AlbumId, ArtistId, Id, PlaylistId, SimplifiedPlaylist};
 use crate::game::Playlist;
+use crate::quiz::{playlist_tracks, QuizError, DISCOGRAPHY_SUFFIX, LIKED_SONGS_ID};
+use crate::{local, playlist_file};
 use futures::StreamExt;
 
 pub async fn spotify_loop(mut rx: mpsc::Receiver<()>, preferences: Arc<Mutex<GamePreferences>>,
                           references: Arc<Mutex<GameReferences>>) {
+  // background task filling the track cache, only one runs at a time
+  let mut cache_task: Option<tokio::task::JoinHandle<()>> = None;
   loop {
     // Always lock references fist to avoid deadlock!
     let r = references.lock().await;
@@ -30,18 +34,81 @@ pub async fn spotify_loop(mut rx: mpsc::Receiver<()>, preferences: Arc<Mutex<Gam
     }
 
     // Refresh playlists
-    if r.spotify_client.has_token().await {
-      let mut p = preferences.lock().await;
-      p.playlists = r.spotify_client
+    let mut playlists: Vec<Playlist> = vec![];
+    let has_token = r.spotify_client.has_token().await;
+    if has_token {
+      let user_playlists: Vec<SimplifiedPlaylist> = r.spotify_client
         .current_user_playlists()
         .filter_map(|playlist| async move { playlist.ok() })
-        .map(|playlist| Playlist { name: playlist.name, id: playlist.id.uri() })
         .collect()
         .await;
+
+      // Load the tracks of new or changed playlists in the background to have them ready when a round starts
+      if cache_task.as_ref().is_none_or(|t| t.is_finished()) {
+        let outdated: Vec<(PlaylistId<'static>, String)> = user_playlists.iter()
+          .filter(|p| !r.track_cache.contains(&p.id.uri(), &p.snapshot_id))
+          .map(|p| (p.id.clone(), p.snapshot_id.clone()))
+          .collect();
+        if !outdated.is_empty() {
+          let spotify = r.spotify_client.clone();
+          let cache = r.track_cache.clone();
+          cache_task = Some(tokio::spawn(async move {
+            for (id, snapshot_id) in outdated {
+              match playlist_tracks(&spotify, id.as_ref()).await {
+                Ok(tracks) => cache.store(&id.uri(), &snapshot_id, &tracks),
+                Err(e) => log::warn!("Could not load tracks of {} for the cache: {}", id.uri(), e)
+              }
+            }
+          }));
+        }
+      }
+
+      playlists = user_playlists.into_iter()
+        .map(|playlist| Playlist { name: playlist.name, id: playlist.id.uri() })
+        .collect();
+      playlists.push(Playlist { name: "Lieblingssongs".to_string(), id: LIKED_SONGS_ID.to_string() });
+    }
+    if let Some(dir) = &r.music_dir {
+      playlists.extend(local::find_playlists(dir));
+    }
+    if let Some(dir) = &r.playlist_dir {
+      playlists.extend(playlist_file::find_playlists(dir));
+    }
+    let has_sources = has_token || r.music_dir.is_some() || r.playlist_dir.is_some() || r.subsonic_client.is_some()
+      || r.mpd_client.is_some();
+    let subsonic_client = r.subsonic_client.clone();
+    let mpd_client = r.mpd_client.clone();
+    // Do not block the game while waiting for the servers
+    drop(r);
+
+    if let Some(client) = &subsonic_client {
+      match client.playlists().await {
+        Ok(p) => playlists.extend(p),
+        Err(e) => log::warn!("Could not get subsonic playlists: {}", e)
+      }
+    }
+    if let Some(client) = &mpd_client {
+      match client.playlists().await {
+        Ok(p) => playlists.extend(p),
+        Err(e) => log::warn!("Could not get MPD playlists: {}", e)
+      }
+    }
+    if has_sources {
+      let mut p = preferences.lock().await;
+      if has_token {
+        playlists.extend(p.spotify_sources.iter().cloned());
+      }
+      p.playlists = playlists;
+      // Remove playlists from the pool that do not exist anymore
+      let available = p.playlists.iter().map(|x| x.id.clone()).collect::<Vec<String>>();
+      p.playlist_pool.retain(|x| available.contains(&x.playlist.id));
+      if let Some(first) = p.playlist_pool.first() {
+        p.selected_playlist = Some(first.playlist.clone());
+      }
       // Select a playlist if none is selected or selected one does not exist
       if (p.selected_playlist.is_none()
-        || p.playlists.iter().find(|x| x.id == p.selected_playlist.as_ref().unwrap().id).is_none())
-        && p.playlists.len() > 0 {
+        || !p.playlists.iter().any(|x| x.id == p.selected_playlist.as_ref().unwrap().id))
+        && !p.playlists.is_empty() {
         log::info!("set selected playlist to first one {:?}", p.playlists[0]);
         p.selected_playlist = Some(p.playlists[0].clone());
       }
@@ -51,7 +118,6 @@ pub async fn spotify_loop(mut rx: mpsc::Receiver<()>, preferences: Arc<Mutex<Gam
         log::debug!("Playlist: {:?}", playlist);
       }
     }
-    drop(r);
 
     match tokio::time::timeout(Duration::from_secs(20), rx.recv()).await {
       Ok(Some(())) => {
@@ -67,6 +133,40 @@ pub async fn spotify_loop(mut rx: mpsc::Receiver<()>, preferences: Arc<Mutex<Gam
   }
 }
 
+/// Get the track pools belonging to an album, artist or playlist given by its Spotify URI or link.
+/// Artists have two pools, one with their top tracks and one with all their songs.
+pub async fn resolve_source(spotify: &AuthCodeSpotify, uri_or_link: &str) -> Result<Vec<Playlist>, QuizError> {
+  let uri = to_uri(uri_or_link.trim())
+    .ok_or(QuizError::RuntimeError(format!("{} is no spotify URI or link", uri_or_link)))?;
+  if uri.starts_with("spotify:album:") {
+    let album = spotify.album(AlbumId::from_uri(&uri)?, None).await?;
+    let artist = album.artists.first().map(|a| a.name.clone()).unwrap_or_default();
+    Ok(vec![Playlist { name: format!("Album: {} - {}", artist, album.name), id: uri }])
+  } else if uri.starts_with("spotify:artist:") {
+    let artist = spotify.artist(ArtistId::from_uri(&uri)?).await?;
+    Ok(vec![
+      Playlist { name: format!("Top-Songs: {}", artist.name), id: uri.clone() },
+      Playlist { name: format!("Diskografie: {}", artist.name), id: format!("{}{}", uri, DISCOGRAPHY_SUFFIX) },
+    ])
+  } else if uri.starts_with("spotify:playlist:") {
+    let playlist = spotify.playlist(PlaylistId::from_uri(&uri)?, None, None).await?;
+    Ok(vec![Playlist { name: playlist.name, id: uri }])
+  } else {
+    Err(QuizError::RuntimeError(format!("{} is no album, artist or playlist", uri)))
+  }
+}
+
+/// Convert links like https://open.spotify.com/album/<id>?si=... to URIs
+fn to_uri(uri_or_link: &str) -> Option<String> {
+  if uri_or_link.starts_with("spotify:") {
+    return Some(uri_or_link.to_string());
+  }
+  let url = url::Url::parse(uri_or_link).ok()?;
+  let segments: Vec<&str> = url.path_segments()?.collect();
+  let pos = segments.iter().position(|s| ["album", "artist", "playlist"].contains(s))?;
+  Some(format!("spotify:{}:{}", segments[pos], segments.get(pos + 1)?))
+}
+
 pub trait CustomSpotifyChecks {
   async fn has_token(&self) -> bool;
 }
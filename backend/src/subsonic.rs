@@ -0,0 +1,323 @@
+use std::time::Duration;
+use rand::distributions::Alphanumeric;
+use rand::{Rng, thread_rng};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use crate::game::{clip_length, GamePreferences, Playlist, Question};
+use crate::player::{clip, AudioPlayer};
+use crate::quiz::{create_questions, pool_playlists, Quiz, QuizError, QuizSource, QuestionAudio, SongQuestion, Track, TrackPool,
+                  FADE_OUT_TIME};
+
+/// Prefix of playlist ids that belong to a Subsonic server
+pub const SUBSONIC_PREFIX: &str = "subsonic:";
+
+/// Subsonic API version that is needed (token authentication was introduced with 1.13.0)
+const API_VERSION: &str = "1.13.0";
+const CLIENT_NAME: &str = "music-quiz";
+
+/// Connection settings for a Subsonic compatible server (Navidrome, Airsonic, ...)
+#[derive(Deserialize, Clone)]
+pub struct SubsonicPrefs {
+  pub url: String,
+  pub user: String,
+  pub password: String,
+}
+
+#[derive(Deserialize)]
+struct ResponseWrapper<T> {
+  #[serde(rename = "subsonic-response")]
+  response: Response<T>,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+  status: String,
+  error: Option<ResponseError>,
+  #[serde(flatten)]
+  content: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+  code: i32,
+  message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PlaylistsContent {
+  playlists: PlaylistList,
+}
+
+#[derive(Deserialize)]
+struct PlaylistList {
+  #[serde(default)]
+  playlist: Vec<PlaylistInfo>,
+}
+
+#[derive(Deserialize)]
+struct PlaylistInfo {
+  id: String,
+  name: String,
+}
+
+#[derive(Deserialize)]
+struct PlaylistContent {
+  playlist: PlaylistEntries,
+}
+
+#[derive(Deserialize)]
+struct PlaylistEntries {
+  #[serde(default)]
+  entry: Vec<Song>,
+}
+
+#[derive(Deserialize)]
+struct Song {
+  id: String,
+  title: Option<String>,
+  artist: Option<String>,
+  album: Option<String>,
+  year: Option<i32>,
+  duration: Option<i64>,
+}
+
+/// Client for the Subsonic REST API, see http://www.subsonic.org/pages/api.jsp
+pub struct SubsonicClient {
+  prefs: SubsonicPrefs,
+  http: reqwest::Client,
+}
+
+impl SubsonicClient {
+  pub fn new(prefs: SubsonicPrefs) -> SubsonicClient {
+    SubsonicClient { prefs, http: reqwest::Client::new() }
+  }
+
+  /// Build a request to an API endpoint with the authentication parameters
+  fn request(&self, endpoint: &str, params: &[(&str, &str)]) -> reqwest::RequestBuilder {
+    // Token authentication: token = md5(password + salt), the salt has to be random for each request
+    let salt: String = thread_rng().sample_iter(&Alphanumeric).take(12).map(char::from).collect();
+    let token = format!("{:x}", md5::compute(format!("{}{}", self.prefs.password, salt)));
+    self.http
+      .get(format!("{}/rest/{}", self.prefs.url.trim_end_matches('/'), endpoint))
+      .query(&[("u", self.prefs.user.as_str()), ("t", &token), ("s", &salt), ("v", API_VERSION),
+               ("c", CLIENT_NAME), ("f", "json")])
+      .query(params)
+  }
+
+  async fn get<T: DeserializeOwned>(&self, endpoint: &str, params: &[(&str, &str)]) -> Result<T, QuizError> {
+    let wrapper: ResponseWrapper<T> = self.request(endpoint, params)
+      .send().await?
+      .error_for_status()?
+      .json().await?;
+    let response = wrapper.response;
+    if response.status != "ok" {
+      let error = response.error
+        .map(|e| format!("{} (code {})", e.message.unwrap_or_default(), e.code))
+        .unwrap_or(response.status);
+      return Err(QuizError::SubsonicAPIError(error));
+    }
+    response.content.ok_or(QuizError::SubsonicAPIError(format!("Empty response from {}", endpoint)))
+  }
+
+  /// Get all playlists of the user
+  pub async fn playlists(&self) -> Result<Vec<Playlist>, QuizError> {
+    let content: PlaylistsContent = self.get("getPlaylists", &[]).await?;
+    Ok(content.playlists.playlist.into_iter()
+      .map(|p| Playlist { name: p.name, id: format!("{}{}", SUBSONIC_PREFIX, p.id) })
+      .collect())
+  }
+
+  /// Get all songs of a playlist that have a title and an artist
+  pub async fn playlist_tracks(&self, id: &str) -> Result<Vec<Track>, QuizError> {
+    let content: PlaylistContent = self.get("getPlaylist", &[("id", id)]).await?;
+    Ok(content.playlist.entry.into_iter()
+      .filter_map(|song| Some(Track {
+        id: song.id,
+        title: song.title?,
+        artists: vec![song.artist?],
+        album: song.album,
+        year: song.year,
+        duration: chrono::Duration::seconds(song.duration.unwrap_or_default()),
+        preview_url: None,
+        popularity: None,
+        cover_url: None,
+      }))
+      .collect())
+  }
+
+  /// Download the audio file of a song
+  pub async fn stream(&self, id: &str) -> Result<bytes::Bytes, QuizError> {
+    Ok(self.request("stream", &[("id", id)])
+      .send().await?
+      .error_for_status()?
+      .bytes().await?)
+  }
+}
+
+/// Quiz using songs from a Subsonic server, which are played on the host
+pub struct SubsonicQuiz {
+  client: std::sync::Arc<SubsonicClient>,
+  songs: Vec<SongQuestion>,
+  questions: Vec<Question>,
+  player: AudioPlayer,
+  // clip of the current question
+  clip: Option<(usize, QuestionAudio)>,
+  clip_length: Duration,
+}
+
+impl SubsonicQuiz {
+  pub fn new(client: std::sync::Arc<SubsonicClient>, player: AudioPlayer) -> SubsonicQuiz {
+    SubsonicQuiz {
+      client,
+      songs: vec![],
+      questions: vec![],
+      player,
+      clip: None,
+      clip_length: Duration::ZERO,
+    }
+  }
+}
+
+impl Quiz for SubsonicQuiz {
+  async fn generate_questions(&mut self, pref: &GamePreferences) -> Result<(), QuizError> {
+    let mut pools = vec![];
+    for p in pool_playlists(pref, QuizSource::Subsonic)? {
+      let playlist_id = &p.playlist.id;
+      let id = playlist_id.strip_prefix(SUBSONIC_PREFIX)
+        .ok_or(QuizError::RuntimeError(format!("{} is no subsonic playlist", playlist_id)))?;
+      let tracks = self.client.playlist_tracks(id).await?;
+      log::info!("Found {} songs in subsonic playlist {}", tracks.len(), playlist_id);
+      pools.push(TrackPool { tracks, weight: p.weight });
+    }
+
+    let (songs, questions) = create_questions(pools, pref)?;
+    self.songs = songs;
+    self.questions = questions;
+    self.clip_length = clip_length(pref);
+    Ok(())
+  }
+
+  /// Downloads the song belonging to the question given by `index` and plays it from a third of the song
+  async fn begin_question_action(&mut self, index: usize, offset: Duration) -> Result<(), QuizError> {
+    if let Some(audio) = self.question_audio(index).await? {
+      self.player.play(audio.data, offset).await?;
+    }
+    let song = &self.songs[index].song;
+    log::info!("Begin question {} {} - {}", index, song.artist_credit(", "), song.title);
+    Ok(())
+  }
+
+  async fn stop_question_action(&mut self, index: usize) -> Result<(), QuizError> {
+    let song = &self.songs.get(index).ok_or(QuizError::RuntimeError("Invalid song index".to_string()))?.song;
+    self.player.fade(FADE_OUT_TIME)?;
+    log::info!("End question {} {} - {}", index, song.artist_credit(", "), song.title);
+    Ok(())
+  }
+
+  async fn cut_question_action(&mut self, _index: usize) -> Result<(), QuizError> {
+    self.player.stop()
+  }
+
+  fn get_questions(&self) -> &Vec<Question> {
+    &self.questions
+  }
+
+  /// Downloads the song and cuts the clip of the question, only the clip of the current question is kept
+  async fn question_audio(&mut self, index: usize) -> Result<Option<QuestionAudio>, QuizError> {
+    if let Some((_, audio)) = self.clip.as_ref().filter(|(i, _)| *i == index) {
+      return Ok(Some(audio.clone()));
+    }
+    let song = self.songs.get(index).ok_or(QuizError::RuntimeError("Invalid song index".to_string()))?;
+    let data = self.client.stream(&song.song.id).await?;
+    let audio = clip(data, song.start.to_std().unwrap_or_default(), self.clip_length).await?;
+    self.clip = Some((index, audio.clone()));
+    Ok(Some(audio))
+  }
+
+  async fn shutdown(&mut self) -> Result<(), QuizError> {
+    self.player.stop()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use axum::extract::Query;
+  use axum::http::header;
+  use axum::response::IntoResponse;
+  use axum::routing::get;
+  use axum::{Json, Router};
+  use serde_json::{json, Value};
+  use super::*;
+
+  const USER: &str = "quiz";
+  const PASSWORD: &str = "sesame";
+
+  /// Check the authentication parameters like a Subsonic server, returns the error response if they are wrong
+  fn check_auth(params: &HashMap<String, String>) -> Option<Json<Value>> {
+    let salt = params.get("s").cloned().unwrap_or_default();
+    let expected = format!("{:x}", md5::compute(format!("{}{}", PASSWORD, salt)));
+    let valid = params.get("u").is_some_and(|u| u == USER) && params.get("t") == Some(&expected)
+      && !salt.is_empty() && params.get("v").is_some_and(|v| v == API_VERSION)
+      && params.get("f").is_some_and(|f| f == "json");
+    if valid {
+      None
+    } else {
+      Some(Json(json!({"subsonic-response": {
+        "status": "failed", "version": API_VERSION, "error": {"code": 40, "message": "Wrong username or password"}
+      }})))
+    }
+  }
+
+  async fn get_playlists(Query(params): Query<HashMap<String, String>>) -> Json<Value> {
+    check_auth(&params).unwrap_or(Json(json!({"subsonic-response": {
+      "status": "ok", "version": API_VERSION,
+      "playlists": {"playlist": [{"id": "1", "name": "Party"}, {"id": "2", "name": "Oldies"}]}
+    }})))
+  }
+
+  async fn stream(Query(params): Query<HashMap<String, String>>) -> axum::response::Response {
+    if let Some(error) = check_auth(&params) {
+      return error.into_response();
+    }
+    match params.get("id").map(String::as_str) {
+      Some("42") => ([(header::CONTENT_TYPE, "audio/mpeg")], vec![1u8, 2, 3]).into_response(),
+      _ => axum::http::StatusCode::NOT_FOUND.into_response(),
+    }
+  }
+
+  /// Start a mock server on a free port and create a client for it
+  async fn client(password: &str) -> SubsonicClient {
+    let app = Router::new()
+      .route("/rest/getPlaylists", get(get_playlists))
+      .route("/rest/stream", get(stream));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    SubsonicClient::new(SubsonicPrefs { url, user: USER.to_string(), password: password.to_string() })
+  }
+
+  #[tokio::test]
+  async fn playlists_with_token_authentication() {
+    let playlists = client(PASSWORD).await.playlists().await.unwrap();
+    let ids: Vec<&str> = playlists.iter().map(|p| p.id.as_str()).collect();
+    assert_eq!(ids, ["subsonic:1", "subsonic:2"]);
+    assert_eq!(playlists[0].name, "Party");
+  }
+
+  #[tokio::test]
+  async fn wrong_password_is_an_api_error() {
+    match client("wrong").await.playlists().await {
+      Err(QuizError::SubsonicAPIError(message)) => assert!(message.contains("code 40")),
+      other => panic!("Expected API error, got {:?}", other.map(|p| p.len())),
+    }
+  }
+
+  #[tokio::test]
+  async fn stream_returns_song_data() {
+    let client = client(PASSWORD).await;
+    let data = client.stream("42").await.unwrap();
+    assert_eq!(data.as_ref(), &[1, 2, 3]);
+    assert!(client.stream("7").await.is_err());
+  }
+}
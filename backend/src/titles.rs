@@ -0,0 +1,141 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use ts_rs::TS;
+use crate::game::GamePreferences;
+
+/// Built-in rule removing a part of song titles that gives away the answer or makes titles of the same song differ
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, EnumIter, TS)]
+#[ts(export)]
+#[ts(export_to = "../shared/")]
+pub enum TitleRule {
+  /// "Song - Remastered 2011", "Song (2009 Remaster)"
+  Remaster,
+  /// "Song (feat. X)", "Song ft. X". "(with X)" is kept, it is also used in titles like "Song (With Strings)".
+  Featuring,
+  /// "Song - Live at Wembley", "Song (Live)"
+  Live,
+  /// "Song - Radio Edit", "Song (Single Version)", "Song - Mono"
+  Edit,
+}
+
+impl TitleRule {
+  /// Regular expressions (case insensitive) for the parts to be removed
+  fn patterns(&self) -> &'static [&'static str] {
+    match self {
+      TitleRule::Remaster => &[
+        r"\s+-\s+[^-]*\bremaster.*$",
+        r"\s*[(\[][^)\]]*\bremaster[^)\]]*[)\]]",
+      ],
+      TitleRule::Featuring => &[
+        r"\s*[(\[]\s*(feat\.?|ft\.|featuring)\s[^)\]]*[)\]]",
+        r"\s+-?\s*(feat\.|ft\.|featuring)\s.*$",
+      ],
+      TitleRule::Live => &[
+        r"\s+-\s+live\b.*$",
+        r"\s*[(\[]\s*live\b[^)\]]*[)\]]",
+      ],
+      TitleRule::Edit => &[
+        r"\s+-\s+((radio|single|album|original)\s+(edit|version|mix)|mono|stereo)(\s+version)?\s*$",
+        r"\s*[(\[]\s*((radio|single|album|original)\s+(edit|version|mix)|mono|stereo)(\s+version)?\s*[)\]]",
+      ],
+    }
+  }
+}
+
+pub fn default_title_rules() -> Vec<TitleRule> {
+  TitleRule::iter().collect()
+}
+
+/// Pipeline of rules applied one after another to song titles
+pub struct TitleCleaner {
+  rules: Vec<Regex>,
+}
+
+impl TitleCleaner {
+  /// Create the pipeline from the enabled built-in rules and the custom patterns of the preferences.
+  /// Invalid custom patterns are skipped.
+  pub fn new(pref: &GamePreferences) -> TitleCleaner {
+    let builtin = pref.title_rules.iter().flat_map(|r| r.patterns().iter().map(|p| p.to_string()));
+    let rules = builtin.chain(pref.title_patterns.iter().cloned())
+      .filter_map(|p| compile(&p).inspect_err(|e| log::warn!("Invalid title pattern {}: {}", p, e)).ok())
+      .collect();
+    TitleCleaner { rules }
+  }
+
+  /// Apply all rules to a title. The original title is kept if nothing would be left of it.
+  pub fn clean(&self, title: &str) -> String {
+    let cleaned = self.rules.iter()
+      .fold(title.to_string(), |t, rule| rule.replace_all(&t, "").into_owned());
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() {
+      title.to_string()
+    } else {
+      cleaned.to_string()
+    }
+  }
+}
+
+/// Compile a pattern of a title rule, they are case insensitive
+pub fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+  RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cleaner(rules: Vec<TitleRule>, patterns: Vec<&str>) -> TitleCleaner {
+    let mut pref = GamePreferences::new();
+    pref.title_rules = rules;
+    pref.title_patterns = patterns.into_iter().map(|p| p.to_string()).collect();
+    TitleCleaner::new(&pref)
+  }
+
+  #[test]
+  fn remaster() {
+    let c = cleaner(vec![TitleRule::Remaster], vec![]);
+    assert_eq!(c.clean("Let It Be - Remastered 2009"), "Let It Be");
+    assert_eq!(c.clean("Help! (2009 Remaster)"), "Help!");
+    assert_eq!(c.clean("Song [Remastered]"), "Song");
+  }
+
+  #[test]
+  fn featuring() {
+    let c = cleaner(vec![TitleRule::Featuring], vec![]);
+    assert_eq!(c.clean("Song (feat. Artist)"), "Song");
+    assert_eq!(c.clean("Song [ft. Artist]"), "Song");
+    assert_eq!(c.clean("Song ft. Artist & Other"), "Song");
+    assert_eq!(c.clean("Song - Featuring Artist"), "Song");
+    assert_eq!(c.clean("Song (With Strings)"), "Song (With Strings)");
+  }
+
+  #[test]
+  fn live() {
+    let c = cleaner(vec![TitleRule::Live], vec![]);
+    assert_eq!(c.clean("Song - Live at Wembley"), "Song");
+    assert_eq!(c.clean("Song (Live)"), "Song");
+    assert_eq!(c.clean("Live and Let Die"), "Live and Let Die");
+    assert_eq!(c.clean("Alive"), "Alive");
+  }
+
+  #[test]
+  fn edit() {
+    let c = cleaner(vec![TitleRule::Edit], vec![]);
+    assert_eq!(c.clean("Song - Radio Edit"), "Song");
+    assert_eq!(c.clean("Song (Single Version)"), "Song");
+    assert_eq!(c.clean("Song - Mono"), "Song");
+    assert_eq!(c.clean("Edit the World"), "Edit the World");
+  }
+
+  #[test]
+  fn rules_are_combined_and_titles_kept() {
+    let c = cleaner(default_title_rules(), vec![r"\s*\(bonus track\)", "("]);
+    assert_eq!(c.clean("Song (feat. X) - Remastered 2011"), "Song");
+    assert_eq!(c.clean("Song (Bonus Track)"), "Song");
+    // Nothing would be left of the title
+    assert_eq!(c.clean("(Live)"), "(Live)");
+    assert_eq!(cleaner(vec![], vec![]).clean("Song - Live"), "Song - Live");
+  }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::quiz::Track;
+
+/// Version of the format of the cache entries. It has to be increased when fields are added to `Track`, entries of
+/// older versions lack them and are not used anymore.
+const FORMAT_VERSION: u32 = 2;
+
+/// Disk cache for the tracks of Spotify playlists, so big playlists do not have to be requested page by page for every
+/// round. Every playlist has its own directory containing one JSON file named after the snapshot id of the playlist,
+/// which changes with every modification of the playlist. Entries are kept in a directory per format version.
+#[derive(Clone)]
+pub struct TrackCache {
+  dir: PathBuf,
+}
+
+impl TrackCache {
+  pub fn new(dir: PathBuf) -> TrackCache {
+    TrackCache { dir: dir.join(format!("v{}", FORMAT_VERSION)) }
+  }
+
+  /// Get the cached tracks of a playlist, `None` if they are not cached or the playlist has changed since
+  pub fn get(&self, playlist_id: &str, snapshot_id: &str) -> Option<Vec<Track>> {
+    let file = fs::File::open(self.file(playlist_id, snapshot_id)).ok()?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+      .inspect_err(|e| log::warn!("Invalid cache entry for {}: {}", playlist_id, e))
+      .ok()
+  }
+
+  /// Check if the tracks of the playlist in the given version are cached
+  pub fn contains(&self, playlist_id: &str, snapshot_id: &str) -> bool {
+    self.file(playlist_id, snapshot_id).is_file()
+  }
+
+  /// Store the tracks of a playlist, older versions of the playlist are removed afterwards.
+  /// The entry is written to a temporary file first and renamed, so readers never see a partly written entry.
+  pub fn store(&self, playlist_id: &str, snapshot_id: &str, tracks: &[Track]) {
+    let dir = self.dir.join(file_name(playlist_id));
+    let file = self.file(playlist_id, snapshot_id);
+    // Unique name, the background task and a starting round may store the same playlist at the same time
+    let tmp = dir.join(format!("{}.{}.tmp", file_name(snapshot_id), rand::random::<u32>()));
+    let result = fs::create_dir_all(&dir)
+      .map_err(serde_json::Error::io)
+      .and_then(|_| write(&tmp, tracks))
+      .and_then(|_| fs::rename(&tmp, &file).map_err(serde_json::Error::io));
+    match result {
+      Ok(_) => {
+        log::info!("Cached {} tracks of {}", tracks.len(), playlist_id);
+        remove_others(&dir, &file);
+      }
+      Err(e) => {
+        log::warn!("Could not cache tracks of {}: {}", playlist_id, e);
+        let _ = fs::remove_file(&tmp);
+      }
+    }
+  }
+
+  fn file(&self, playlist_id: &str, snapshot_id: &str) -> PathBuf {
+    self.dir.join(file_name(playlist_id)).join(format!("{}.json", file_name(snapshot_id)))
+  }
+}
+
+fn write(path: &Path, tracks: &[Track]) -> Result<(), serde_json::Error> {
+  let mut writer = std::io::BufWriter::new(fs::File::create(path).map_err(serde_json::Error::io)?);
+  serde_json::to_writer(&mut writer, tracks)?;
+  writer.flush().map_err(serde_json::Error::io)
+}
+
+/// Remove the entries of older versions of a playlist in its directory
+fn remove_others(dir: &Path, keep: &Path) {
+  let Ok(entries) = fs::read_dir(dir) else { return };
+  for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+    // Temporary files of a store running at the same time are left alone
+    if path != keep && path.extension().is_some_and(|ext| ext == "json") {
+      if let Err(e) = fs::remove_file(&path) {
+        log::warn!("Could not remove old cache entry {:?}: {}", path, e);
+      }
+    }
+  }
+}
+
+/// Ids may contain characters that are not allowed in file names (snapshot ids are base64), so they are hex encoded.
+/// Replacing these characters instead would map different ids to the same file.
+fn file_name(id: &str) -> String {
+  id.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn file_names_are_distinct() {
+    assert_eq!(file_name("spotify:playlist:1/A"), "73706f746966793a706c61796c6973743a312f41");
+    assert_ne!(file_name("AAA+BB="), file_name("AAA/BB="));
+    assert_ne!(file_name("AAA+BB="), file_name("AAA_BB_"));
+  }
+}
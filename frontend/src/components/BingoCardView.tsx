@@ -0,0 +1,43 @@
+import React, {useEffect, useState} from "react";
+import './AnswerInput.scss';
+import {BingoCard} from "../../../shared/BingoCard";
+import {Bingo} from "../../../shared/Bingo";
+
+type BingoCardProps = {
+  user: string,
+  bingo: Bingo
+}
+
+export const BingoCardView: React.FC<BingoCardProps> = ({user, bingo}) => {
+  const [card, setCard] = useState<BingoCard | null>(null);
+
+  // Squares are marked after a song has been played, so the card is loaded again for every song
+  useEffect(() => {
+    fetch("/bingo_card?user=" + encodeURIComponent(user))
+      .then((response) => response.json())
+      .then((data: BingoCard) => setCard(data), () => console.log("error on getting bingo card"));
+  }, [user, bingo.played.length]);
+
+  const claimBingo = () => {
+    fetch("/claim_bingo?user=" + encodeURIComponent(user), {
+      'method': 'POST'
+    }).then(r => console.log(r));
+  }
+
+  if (card === null) {
+    return <h2>Lade Bingokarte...</h2>;
+  }
+  return (
+    <div className="answer-input">
+      <div className="bingo-card">
+        {card.squares.map((square, index) => {
+          return <div key={index} className={`bingo-square ${card.marked[index] && 'marked'}`}>{square}</div>;
+        })}
+      </div>
+      <button disabled={bingo.winners.includes(user)} onClick={claimBingo}>Bingo!</button>
+      {bingo.winners.map((winner, index) => {
+        return <div key={winner} className="answer-hint">{index + 1}. Bingo: {winner}</div>;
+      })}
+    </div>
+  );
+}
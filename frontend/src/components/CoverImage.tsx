@@ -0,0 +1,60 @@
+import React, {useEffect, useRef, useState} from "react";
+import './CoverImage.scss';
+import {QuestionMedia} from "../../../shared/QuestionMedia";
+
+type CoverImageProps = {
+  media: QuestionMedia,
+  // Server times at which the cover starts to get sharper and is fully visible
+  action_start: number,
+  next_action: number,
+  timediff: number,
+  revealed: boolean
+}
+
+const UPDATE_PERIOD = 100;
+const MAX_BLUR = 30;
+const MIN_PIXELS = 6;
+const SIZE = 300;
+
+// Album cover of a question that is blurred or pixelated at first and gets sharper until the time to answer is over
+export const CoverImage: React.FC<CoverImageProps> = ({media, action_start, next_action, timediff, revealed}) => {
+  const image = useRef<HTMLImageElement>(null);
+  const canvas = useRef<HTMLCanvasElement>(null);
+  const [now, setNow] = useState<number>(Date.now());
+  const [loaded, setLoaded] = useState<boolean>(false);
+
+  useEffect(() => {
+    const interval = setInterval(() => setNow(Date.now()), UPDATE_PERIOD);
+    return () => clearInterval(interval);
+  }, []);
+
+  const elapsed = now - timediff - action_start;
+  const progress = revealed ? 1 : Math.min(1, Math.max(0, elapsed / Math.max(1, next_action - action_start)));
+
+  useEffect(() => {
+    const context = canvas.current?.getContext("2d");
+    if (media.effect !== "Pixelate" || !context || !image.current || !loaded) {
+      return;
+    }
+    // Draw the cover in few pixels and scale it up without smoothing
+    const pixels = Math.round(MIN_PIXELS + (SIZE - MIN_PIXELS) * progress * progress);
+    context.imageSmoothingEnabled = false;
+    context.clearRect(0, 0, SIZE, SIZE);
+    context.drawImage(image.current, 0, 0, pixels, pixels);
+    context.drawImage(context.canvas, 0, 0, pixels, pixels, 0, 0, SIZE, SIZE);
+  }, [media, progress, loaded]);
+
+  if (media.effect === "Pixelate") {
+    return (
+      <div className="cover-container">
+        <img ref={image} src={media.url} alt="" className="cover-source" onLoad={() => setLoaded(true)}/>
+        <canvas ref={canvas} width={SIZE} height={SIZE} className="cover"/>
+      </div>
+    );
+  }
+  return (
+    <div className="cover-container">
+      <img src={media.url} alt="Cover" className="cover" style={{filter: `blur(${MAX_BLUR * (1 - progress)}px)`}}/>
+    </div>
+  );
+}
@@ -0,0 +1,25 @@
+import React from "react";
+import './AnswerInput.scss';
+import {UserAnswerExposed} from "../../../shared/UserAnswerExposed";
+
+type GivenAnswersProps = {
+  answers: UserAnswerExposed[],
+  user: string,
+  // Text shown for the id of an answer
+  format: (answer_id: string) => string
+}
+
+// Answers of all players, typed answers are hidden until the question is over
+export const GivenAnswers: React.FC<GivenAnswersProps> = ({answers, user, format}) => {
+  return (
+    <div className="given-answers">
+      {answers.map((a) => {
+        return (
+          <div key={a.user} className={`given-answer ${a.user === user && 'own'}`}>
+            <span className="button-mark">{a.user}</span> {a.answer_id === "" ? "Antwort abgegeben" : format(a.answer_id)}
+          </div>
+        );
+      })}
+    </div>
+  );
+}
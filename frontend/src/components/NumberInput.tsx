@@ -0,0 +1,21 @@
+import React, {useState} from "react";
+import './AnswerInput.scss';
+
+type NumberInputProps = {
+  min: number,
+  max: number,
+  onSubmit: (value: number) => void
+}
+
+export const NumberInput: React.FC<NumberInputProps> = ({min, max, onSubmit}) => {
+  const [value, setValue] = useState<number>(Math.round((min + max) / 2));
+
+  return (
+    <div className="answer-input">
+      <input type="range" value={value} min={min} max={max} step={1}
+             onChange={(e) => setValue(Number(e.target.value))}/>
+      <div className="answer-value">{value}</div>
+      <button onClick={() => onSubmit(value)}>Antworten</button>
+    </div>
+  );
+}
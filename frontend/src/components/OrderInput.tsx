@@ -0,0 +1,38 @@
+import React, {useState} from "react";
+import './AnswerInput.scss';
+import {AnswerExposed} from "../../../shared/AnswerExposed";
+
+type OrderInputProps = {
+  answers: AnswerExposed[],
+  // Ids of the answers from the oldest to the newest song
+  onSubmit: (ids: string[]) => void
+}
+
+export const OrderInput: React.FC<OrderInputProps> = ({answers, onSubmit}) => {
+  const [order, setOrder] = useState<AnswerExposed[]>(answers);
+
+  // Swap the answer at the given index with the one above
+  const moveUp = (index: number) => {
+    if (index > 0) {
+      const moved = [...order];
+      [moved[index - 1], moved[index]] = [moved[index], moved[index - 1]];
+      setOrder(moved);
+    }
+  }
+
+  return (
+    <div className="answer-input">
+      <div className="answer-hint">Ältester Song zuerst</div>
+      {order.map((answer, index) => {
+        return (
+          <div key={answer.id} className="order-entry">
+            <div className="order-text">{index + 1}. {answer.text}</div>
+            <button className="order-button" disabled={index === 0} onClick={() => moveUp(index)}>▲</button>
+            <button className="order-button" disabled={index === order.length - 1} onClick={() => moveUp(index + 1)}>▼</button>
+          </div>
+        );
+      })}
+      <button onClick={() => onSubmit(order.map((a) => a.id))}>Antworten</button>
+    </div>
+  );
+}
@@ -0,0 +1,40 @@
+import React, {useState} from "react";
+import './AnswerInput.scss';
+import {GameButton} from "./GameButton";
+import {QuestionPart} from "../../../shared/QuestionPart";
+
+type PartsInputProps = {
+  parts: QuestionPart[],
+  // Answer id or typed text for every part, empty for parts that are not answered
+  onSubmit: (parts: string[]) => void
+}
+
+export const PartsInput: React.FC<PartsInputProps> = ({parts, onSubmit}) => {
+  const [given, setGiven] = useState<string[]>(parts.map(() => ""));
+
+  const setPart = (index: number, value: string) => {
+    setGiven(given.map((g, i) => i === index ? value : g));
+  }
+
+  return (
+    <div className="answer-input">
+      {parts.map((part, index) => {
+        return (
+          <div key={part.text} className="answer-part">
+            <h2>{part.text}</h2>
+            {part.answers.length === 0 ?
+              <input className="answer-text" value={given[index]} placeholder={part.text}
+                     onChange={(e) => setPart(index, e.target.value)}/> :
+              part.answers.map((answer) => {
+                return (
+                  <GameButton key={answer.id} onClick={() => setPart(index, answer.id)} correct={false} wrong={false}
+                              selected={given[index] === answer.id} text={answer.text} markings={undefined}/>
+                );
+              })}
+          </div>
+        );
+      })}
+      <button disabled={given.every((g) => g.trim() === "")} onClick={() => onSubmit(given)}>Antworten</button>
+    </div>
+  );
+}
@@ -0,0 +1,44 @@
+import React, {useEffect, useRef} from "react";
+import {AudioInfo} from "../../../shared/AudioInfo";
+
+type QuestionAudioProps = {
+  audio: AudioInfo | null,
+  playing: boolean,
+  timediff: number
+}
+
+// Plays the audio of the current question in sync with the server, start_time is given in server time
+export const QuestionAudio: React.FC<QuestionAudioProps> = ({audio, playing, timediff}) => {
+  const player = useRef<HTMLAudioElement>(null);
+
+  useEffect(() => {
+    const element = player.current;
+    if (element === null) {
+      return;
+    }
+    if (audio === null || !playing) {
+      element.pause();
+      return;
+    }
+
+    const start = audio.start_time + timediff;
+    const timeout = setTimeout(() => {
+      // Skip the part that has been missed if the state arrived late
+      const late = Math.max(0, Date.now() - start);
+      element.currentTime = (audio.position + late) / 1000;
+      element.play().catch(() => console.log("could not play audio"));
+    }, Math.max(0, start - Date.now()));
+    // Stages of a progressive reveal are only played for their length
+    const stop = audio.length !== null ? setTimeout(() => element.pause(), start + audio.length - Date.now()) : null;
+
+    return () => {
+      clearTimeout(timeout);
+      if (stop !== null) {
+        clearTimeout(stop);
+      }
+      element.pause();
+    };
+  }, [audio?.url, audio?.start_time, audio?.position, audio?.length, playing]);
+
+  return <audio ref={player} src={audio?.url} preload="auto"/>;
+}
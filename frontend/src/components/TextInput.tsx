@@ -0,0 +1,25 @@
+import React, {useState} from "react";
+import './AnswerInput.scss';
+
+type TextInputProps = {
+  onSubmit: (text: string) => void
+}
+
+export const TextInput: React.FC<TextInputProps> = ({onSubmit}) => {
+  const [text, setText] = useState<string>("");
+
+  const submit = () => {
+    if (text.trim() !== "") {
+      onSubmit(text);
+    }
+  }
+
+  return (
+    <div className="answer-input">
+      <input className="answer-text" value={text} placeholder={"Antwort"} autoFocus={true}
+             onChange={(e) => setText(e.target.value)}
+             onKeyDown={(e) => e.key === "Enter" && submit()}/>
+      <button onClick={submit}>Antworten</button>
+    </div>
+  );
+}
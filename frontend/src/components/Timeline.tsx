@@ -0,0 +1,30 @@
+import React from "react";
+import './AnswerInput.scss';
+import {TimelineCard} from "../../../shared/TimelineCard";
+
+type TimelineProps = {
+  cards: TimelineCard[],
+  // Insert the current song at the given position, the timeline is only shown if not given
+  onInsert?: (position: number) => void
+}
+
+export const Timeline: React.FC<TimelineProps> = ({cards, onInsert}) => {
+  const slot = (position: number) => onInsert &&
+    <button className="timeline-slot" onClick={() => onInsert(position)}>Hier einordnen</button>;
+
+  return (
+    <div className="answer-input">
+      {slot(0)}
+      {cards.map((card, index) => {
+        return (
+          <React.Fragment key={card.song}>
+            <div className="timeline-card">
+              <span className="timeline-year">{card.year}</span> {card.song}
+            </div>
+            {slot(index + 1)}
+          </React.Fragment>
+        );
+      })}
+    </div>
+  );
+}
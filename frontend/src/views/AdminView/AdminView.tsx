@@ -12,6 +12,18 @@ enum ScoreMode {
   Order = "Order"
 }
 
+// Same limits as in the backend (quiz.rs)
+const MIN_ANSWER_COUNT = 2;
+const MAX_ANSWER_COUNT = 8;
+
+const BINGO_CONTENTS: SingleSelectionElement[] = [
+  {name: "Titles", description: "Titel"},
+  {name: "Artists", description: "Künstler"}];
+
+const COVER_EFFECTS: SingleSelectionElement[] = [
+  {name: "Blur", description: "Unscharf"},
+  {name: "Pixelate", description: "Verpixelt"}];
+
 const SCORE_MODES: SingleSelectionElement[] = [
   {name: ScoreMode.TimeFunction, description: "Zeit (Funktion)"},
   {name: ScoreMode.TimeLinear, description: "Zeit (linear)"},
@@ -138,6 +150,12 @@ export const AdminView: React.FC = () => {
             <Slider name={"rounds"} description={"Anzahl Runden"} value={preferences.rounds}
                     min={1}
                     max={30} unit="" onChange={(v) => savePreference("rounds", String(v))}/>
+            <Slider name={"text_tolerance"} description={"Fehler bei Texteingabe"} value={preferences.text_tolerance}
+                    min={0}
+                    max={50} unit="%" onChange={(v) => savePreference("text_tolerance", String(v))}/>
+            <Slider name={"answer_count"} description={"Anzahl Antworten"} value={preferences.answer_count}
+                    min={MIN_ANSWER_COUNT}
+                    max={MAX_ANSWER_COUNT} unit="" onChange={(v) => savePreference("answer_count", String(v))}/>
           </div>
 
           <div className="checkbox-container">
@@ -147,6 +165,12 @@ export const AdminView: React.FC = () => {
                      onChange={() => savePreference("hide_answers", String(!preferences.hide_answers))}/>
               Antworten bis Auflösung verbergen
             </label>
+            <label>
+              <input checked={preferences.answer_count_ramp}
+                     type="checkbox"
+                     onChange={() => savePreference("answer_count_ramp", String(!preferences.answer_count_ramp))}/>
+              Mehr Antworten bei späteren Fragen
+            </label>
             <label>
               <input checked={preferences.ask_for_artist}
                      type="checkbox"
@@ -159,6 +183,66 @@ export const AdminView: React.FC = () => {
                      onChange={() => savePreference("ask_for_title", String(!preferences.ask_for_title))}/>
               Nach Titel fragen
             </label>
+            <label>
+              <input checked={preferences.ask_for_album}
+                     type="checkbox"
+                     onChange={() => savePreference("ask_for_album", String(!preferences.ask_for_album))}/>
+              Nach Album fragen
+            </label>
+            <label>
+              <input checked={preferences.ask_for_artist_and_title}
+                     type="checkbox"
+                     onChange={() => savePreference("ask_for_artist_and_title", String(!preferences.ask_for_artist_and_title))}/>
+              Nach Künstler und Titel zugleich fragen
+            </label>
+            <label>
+              <input checked={preferences.ask_for_year}
+                     type="checkbox"
+                     onChange={() => savePreference("ask_for_year", String(!preferences.ask_for_year))}/>
+              Nach Erscheinungsjahr fragen
+            </label>
+            <label>
+              <input checked={preferences.free_text}
+                     type="checkbox"
+                     onChange={() => savePreference("free_text", String(!preferences.free_text))}/>
+              Antworten eintippen
+            </label>
+            <label>
+              <input checked={preferences.ask_for_order}
+                     type="checkbox"
+                     onChange={() => savePreference("ask_for_order", String(!preferences.ask_for_order))}/>
+              Songs nach Erscheinungsdatum ordnen
+            </label>
+            <label>
+              <input checked={preferences.timeline_mode}
+                     type="checkbox"
+                     onChange={() => savePreference("timeline_mode", String(!preferences.timeline_mode))}/>
+              Zeitstrahl bauen
+            </label>
+            <label>
+              <input checked={preferences.bingo_mode}
+                     type="checkbox"
+                     onChange={() => savePreference("bingo_mode", String(!preferences.bingo_mode))}/>
+              Musik-Bingo spielen
+            </label>
+            <label>
+              <input checked={preferences.ask_for_cover}
+                     type="checkbox"
+                     onChange={() => savePreference("ask_for_cover", String(!preferences.ask_for_cover))}/>
+              Albumcover erraten
+            </label>
+            <label>
+              <input checked={preferences.progressive_reveal}
+                     type="checkbox"
+                     onChange={() => savePreference("progressive_reveal", String(!preferences.progressive_reveal))}/>
+              Songs stufenweise anspielen
+            </label>
+            <label>
+              <input checked={preferences.buzzer_mode}
+                     type="checkbox"
+                     onChange={() => savePreference("buzzer_mode", String(!preferences.buzzer_mode))}/>
+              Buzzer nutzen
+            </label>
           </div>
         </fieldset>
 
@@ -169,6 +253,20 @@ export const AdminView: React.FC = () => {
                            options={SCORE_MODES} onChange={(s) => savePreference("scoremode", s)}/>
         </fieldset>
 
+        <fieldset>
+          <legend>Albumcover</legend>
+          <SingleSelection selected={preferences.cover_effect}
+                           name="cover_effect" display="Albumcover"
+                           options={COVER_EFFECTS} onChange={(s) => savePreference("cover_effect", s)}/>
+        </fieldset>
+
+        <fieldset>
+          <legend>Bingo</legend>
+          <SingleSelection selected={preferences.bingo_content}
+                           name="bingo_content" display="Bingo"
+                           options={BINGO_CONTENTS} onChange={(s) => savePreference("bingo_content", s)}/>
+        </fieldset>
+
 
 
 
@@ -2,15 +2,27 @@ import React from 'react';
 import './GameView.scss';
 import {GameButton} from "../../components/GameButton";
 import {TimeBar} from "../../components/TimeBar";
+import {QuestionAudio} from "../../components/QuestionAudio";
+import {NumberInput} from "../../components/NumberInput";
+import {TextInput} from "../../components/TextInput";
+import {OrderInput} from "../../components/OrderInput";
+import {Timeline} from "../../components/Timeline";
+import {BingoCardView} from "../../components/BingoCardView";
+import {CoverImage} from "../../components/CoverImage";
+import {PartsInput} from "../../components/PartsInput";
+import {GivenAnswers} from "../../components/GivenAnswers";
 import {ResultView} from "../ResultView/ResultView";
 import {Link} from 'react-router-dom';
 import {globalStateContext} from "../GlobalStateProvider/GlobalStateProvider";
 import {GameState} from "../../../../shared/GameState";
 import {UserAnswerExposed} from "../../../../shared/UserAnswerExposed";
+import {AnswerFromUser} from "../../../../shared/AnswerFromUser";
+import {Question} from "../../../../shared/Question";
+import {BuzzFromUser} from "../../../../shared/BuzzFromUser";
 import {WebSocketMessage} from "../../../../shared/WebSocketMessage";
 import {TimeRequest} from "../../../../shared/TimeRequest";
 import {TimeAnswer} from "../../../../shared/TimeAnswer";
-import {DEFAULT_GAME_STATE, SOCKET_CHECK_RATE, TEST_GAME_STATE, TIME_SYNC_PERIOD} from "./GameViewConstants";
+import {DEFAULT_GAME_STATE, REVEAL_STAGES, SOCKET_CHECK_RATE, TEST_GAME_STATE, TIME_SYNC_PERIOD} from "./GameViewConstants";
 import {config} from "../../constants";
 
 // Never true in production
@@ -134,11 +146,20 @@ export class GameView extends React.Component<any, GameViewState> {
   }
 
   onClick(id: string) {
+    this.sendAnswer({id: id});
+    console.log("clicked" + id);
+  }
+
+  // Send an answer, only the fields belonging to the kind of the question are given
+  sendAnswer(answer: Partial<AnswerFromUser>) {
     const {state} = this.context;
-    const data = {
-      "id": id,
-      "timestamp": Date.now() - Number(this.timediff),
-      "user": state.user
+    const data : AnswerFromUser = {
+      id: answer.id ?? "",
+      position: answer.position ?? null,
+      text: answer.text ?? null,
+      parts: answer.parts ?? null,
+      timestamp: Date.now() - Number(this.timediff),
+      user: state.user
     }
     const message : WebSocketMessage = {
       message_type: "Answer",
@@ -146,9 +167,63 @@ export class GameView extends React.Component<any, GameViewState> {
     }
     if(this.socket) {
       this.socket.send(JSON.stringify(message));
-      console.log("sent" + id);
+      console.log("sent" + data.id);
     }
-    console.log("clicked" + id);
+  }
+
+  // Buzz to answer alone in buzzer mode
+  buzz() {
+    const {state} = this.context;
+    const data : BuzzFromUser = {
+      user: state.user
+    }
+    const message : WebSocketMessage = {
+      message_type: "Buzz",
+      data: JSON.stringify(data)
+    }
+    if(this.socket) {
+      this.socket.send(JSON.stringify(message));
+    }
+  }
+
+  // Inputs for questions that are not answered by choosing one of the answers
+  renderInputs(question: Question, data: GameState, user: string) {
+    const answered = data.given_answers.some((a) => a.user === user);
+    const kind = question.kind;
+    const cards = data.timelines.find((t) => t.player === user)?.cards ?? [];
+    if (kind === "Bingo") {
+      return data.bingo && <BingoCardView user={user} bingo={data.bingo}/>;
+    } else if (answered || data.status === "InGameWaitForNextQuestion") {
+      // Order answers are given as ids of the songs, timeline answers as position in the timeline and answers of
+      // several parts line by line
+      const format = kind === "Order" ?
+        (id: string) => id.split(",").map((i) => question.answers.find((a) => a.id === i)?.text ?? i).join(" < ") :
+        kind === "Timeline" ? (id: string) => "Platz " + (Number(id) + 1) :
+        kind === "Parts" ? (id: string) => id.split("\n")
+          .map((g, i) => question.parts[i]?.answers.find((a) => a.id === g)?.text ?? g)
+          .filter((g) => g !== "").join(" - ") :
+        (id: string) => id;
+      return (
+        <div>
+          <GivenAnswers answers={data.given_answers} user={user} format={format}/>
+          {kind === "Timeline" && <Timeline cards={cards}/>}
+        </div>
+      );
+    } else if (typeof kind === "object" && "Number" in kind) {
+      return <NumberInput key={question.index} min={kind.Number.min} max={kind.Number.max}
+                          onSubmit={(n) => this.sendAnswer({id: String(n)})}/>;
+    } else if (kind === "Text") {
+      return <TextInput key={question.index} onSubmit={(text) => this.sendAnswer({text: text})}/>;
+    } else if (kind === "Order") {
+      return <OrderInput key={question.index} answers={question.answers}
+                         onSubmit={(ids) => this.sendAnswer({id: ids.join(",")})}/>;
+    } else if (kind === "Timeline") {
+      return <Timeline cards={cards} onInsert={(position) => this.sendAnswer({position: position})}/>;
+    } else if (kind === "Parts") {
+      return <PartsInput key={question.index} parts={question.parts}
+                         onSubmit={(parts) => this.sendAnswer({parts: parts})}/>;
+    }
+    return null;
   }
 
   render() {
@@ -160,8 +235,12 @@ export class GameView extends React.Component<any, GameViewState> {
     if (data != null) {
       switch (data.status) {
         case "InGameAnswerPending":
+        case "InGameBuzzerOpen":
+        case "InGameBuzzed":
         case "InGameWaitForNextQuestion":
-          const buttons = data.current_question?.answers.map((answer: { id: string; text: string; }) => {
+          const pending = data.status !== "InGameWaitForNextQuestion";
+          const question = data.current_question;
+          const buttons = question !== null && question.kind !== "Choice" ? this.renderInputs(question, data, context.user) : question?.answers.map((answer: { id: string; text: string; }) => {
             const is_selected: boolean = data.given_answers?.find((x: UserAnswerExposed) => x.user === context.user && answer.id === x.answer_id) !== undefined;
             const is_correct_answer: boolean = answer.id === data.current_question?.correct;
             const is_correct_known: boolean = data.current_question?.correct !== null;
@@ -182,14 +261,29 @@ export class GameView extends React.Component<any, GameViewState> {
             <div>
               <label className={`game-progress`}>{data.current_question !== null ? (data.current_question.index + 1) : ""}/{data.current_question?.total_questions}</label>
               <h1>
-                {data.status === "InGameAnswerPending" && data.current_question?.text}
+                {pending && data.current_question?.text}
                 {data.status === "InGameWaitForNextQuestion" && "Lösung: " + data.current_question?.solution}
               </h1>
+              {data.reveal_stage !== null && pending &&
+                <h2 className="reveal-stage">
+                  Stufe {data.reveal_stage + 1}/{REVEAL_STAGES.length}: {REVEAL_STAGES[data.reveal_stage]}s
+                </h2>}
+              {question?.media &&
+                <CoverImage key={question.index} media={question.media} action_start={data.action_start}
+                            next_action={data.next_action} timediff={this.timediff}
+                            revealed={data.status === "InGameWaitForNextQuestion"}/>}
               <div className={'button_container'}>
                 <TimeBar key={Math.random()} total_time={data.next_action - data.action_start}
                          elapsed={Date.now() - this.timediff - data.action_start}
-                         colorful={data.status === "InGameAnswerPending"}/>
-                {buttons}
+                         colorful={pending}/>
+                {data.status === "InGameBuzzerOpen" &&
+                  <button className="buzzer" disabled={data.locked_out.includes(context.user)} onClick={() => this.buzz()}>
+                    {data.locked_out.includes(context.user) ? "Gesperrt" : "Buzzer"}
+                  </button>}
+                {data.status === "InGameBuzzed" && data.buzzer !== context.user &&
+                  <h2>{data.buzzer} hat gebuzzert und antwortet...</h2>}
+                {(data.status === "InGameAnswerPending" || data.status === "InGameWaitForNextQuestion" ||
+                  (data.status === "InGameBuzzed" && data.buzzer === context.user)) && buttons}
               </div>
               <hr/>
               <ResultView title="Punktestand" small={true} results={data.players}/>
@@ -235,6 +329,9 @@ export class GameView extends React.Component<any, GameViewState> {
           </Link>
         </div>
         {content}
+        {context.play_audio &&
+          <QuestionAudio audio={data.audio} timediff={this.timediff}
+                         playing={data.status === "InGameAnswerPending" || data.status === "InGameBuzzerOpen"}/>}
       </div>
     );
   }
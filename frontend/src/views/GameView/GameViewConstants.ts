@@ -2,6 +2,8 @@ import {GameState} from "../../../../shared/GameState";
 
 export const TIME_SYNC_PERIOD = 500;
 export const SOCKET_CHECK_RATE = 1000;
+// Seconds played in the stages of a progressive reveal
+export const REVEAL_STAGES = [1, 2, 4, 8];
 
 export const DEFAULT_GAME_STATE : GameState = {
   status: "Shutdown",
@@ -10,7 +12,13 @@ export const DEFAULT_GAME_STATE : GameState = {
   current_question: null,
   given_answers: [],
   players: [],
-  hide_answers: false
+  hide_answers: false,
+  timelines: [],
+  bingo: null,
+  reveal_stage: null,
+  buzzer: null,
+  locked_out: [],
+  audio: null
 }
 
 export const TEST_GAME_STATE : GameState = {
@@ -19,6 +27,7 @@ export const TEST_GAME_STATE : GameState = {
   next_action: 1659900278643,
   current_question: {
     text: "Wie heißt der Titel?",
+    kind: "Choice",
     answers: [
       {
         text: "The Bottom",
@@ -40,7 +49,9 @@ export const TEST_GAME_STATE : GameState = {
     correct: "Blind Man",
     solution: null,
     index: 1,
-    total_questions: 5
+    total_questions: 5,
+    parts: [],
+    media: null
   },
   players: [
     {
@@ -111,23 +122,33 @@ export const TEST_GAME_STATE : GameState = {
     {
       answer_id: "Help",
       user: "Lars",
-      ts: 1659900277055
+      ts: 1659900277055,
+      stage: null
     },
     {
       answer_id: "Help",
       user: "Hanswurst",
-      ts: 1659900277055
+      ts: 1659900277055,
+      stage: null
     },
     {
       answer_id: "Help",
       user: "Myje",
-      ts: 1659900277055
+      ts: 1659900277055,
+      stage: null
     },
     {
       answer_id: "MC Thunder",
       user: "Nils",
-      ts: 1659900277055
+      ts: 1659900277055,
+      stage: null
     }
   ],
-  hide_answers: false
+  hide_answers: false,
+  timelines: [],
+  bingo: null,
+  reveal_stage: null,
+  buzzer: null,
+  locked_out: [],
+  audio: null
 }
\ No newline at end of file
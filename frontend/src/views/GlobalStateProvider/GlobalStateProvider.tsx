@@ -4,11 +4,14 @@ const LOCALSTORE_STATE = "globalstate";
 
 const defaultGlobalState = {
   user: "",
+  play_audio: false,
 };
 
 export interface GlobalStateContextType {
   state: {
-    user: string
+    user: string,
+    // Play the audio of the questions on this device
+    play_audio: boolean
   }
   updateState: (newState: object) => void
 }
@@ -15,6 +15,11 @@ export const LoginView: React.FC = () => {
       {/*<h1>Namen eingeben</h1>*/}
       <input className="form__field" onChange={(e) => setName(e.target.value)} defaultValue={name ? name : ""} placeholder={"Name"} id={'name'} name={"name"}/>
       <label htmlFor="name" className="form__label">Name</label>
+      <label className="audio_checkbox">
+        <input checked={state.play_audio} type="checkbox"
+               onChange={() => updateState({play_audio: !state.play_audio})}/>
+        Musik auf diesem Gerät abspielen
+      </label>
       <button className={"login_button"} type="submit" onClick={
         () => {
           if (name !== "" && name != null) {